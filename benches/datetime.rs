@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use handlebars::Handlebars;
+use handlebars_chrono::HandlebarsChronoDateTime;
+use serde_json::json;
+use std::hint::black_box;
+
+/// Typical templates, from a bare default to an invoice footer with a few modifiers
+const TEMPLATES: [(&str, &str); 4] = [
+    ("default", r#"{{datetime}}"#),
+    ("timestamp", r#"{{datetime from_timestamp=created_at output_format="%d.%m.%Y"}}"#),
+    ("preset", r#"{{datetime from_rfc3339=created_at format="http_date"}}"#),
+    (
+        "modifiers",
+        r#"{{datetime from_rfc3339=created_at with_timezone="+02:00" add_days="14" with_hour="23" output_format="%B %@o, %Y"}}"#,
+    ),
+];
+
+fn bench_templates(c: &mut Criterion) {
+    let mut h = Handlebars::new();
+    h.register_helper("datetime", Box::new(HandlebarsChronoDateTime::default()));
+
+    for (name, template) in TEMPLATES {
+        h.register_template_string(name, template).expect("Invalid template");
+    }
+
+    let timestamp = json!({ "created_at": "618658211" });
+    let rfc3339 = json!({ "created_at": "1989-08-09T09:30:11+02:00" });
+
+    let mut group = c.benchmark_group("datetime");
+    for (name, _template) in TEMPLATES {
+        let data = if name == "timestamp" { &timestamp } else { &rfc3339 };
+
+        group.bench_function(name, |b| {
+            b.iter(|| h.render(black_box(name), black_box(data)).expect("Render error"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_templates);
+criterion_main!(benches);
@@ -0,0 +1,223 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+use handlebars::JsonValue;
+use serde_json::json;
+use std::str::FromStr;
+
+use crate::calendar::{self, MonthOverflow};
+use crate::duration::{self, Unit};
+use crate::styles;
+
+/// Smallest unit of the `age_since` finalizer, `days` by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AgePrecision {
+    Minutes,
+    Hours,
+    #[default]
+    Days,
+    Months,
+    Years,
+}
+
+impl FromStr for AgePrecision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "years" | "year" => Ok(Self::Years),
+            "months" | "month" => Ok(Self::Months),
+            "days" | "day" => Ok(Self::Days),
+            "hours" | "hour" => Ok(Self::Hours),
+            "minutes" | "minute" => Ok(Self::Minutes),
+            other => Err(format!("Expected `years`, `months`, `days`, `hours` or `minutes`, got: {}", other)),
+        }
+    }
+}
+
+impl AgePrecision {
+    /// Every unit, from the largest to the smallest
+    const ALL: [AgePrecision; 5] = [Self::Years, Self::Months, Self::Days, Self::Hours, Self::Minutes];
+
+    fn key(self) -> &'static str {
+        match self {
+            Self::Years => "years",
+            Self::Months => "months",
+            Self::Days => "days",
+            Self::Hours => "hours",
+            Self::Minutes => "minutes",
+        }
+    }
+
+    /// Name of the unit in the language of the locale, English without a locale
+    ///
+    /// `None` for languages without bundled names, the same ones as for the `long` duration style.
+    fn name(self, count: u64, locale: Option<&str>) -> Option<&'static str> {
+        let language = styles::language(locale);
+        // French uses the singular for 0 too
+        let one = count == 1 || (count == 0 && language == "fr");
+
+        let (singular, plural) = match (language, self) {
+            (_, Self::Days) => return Unit::Days.name(count, locale),
+            (_, Self::Hours) => return Unit::Hours.name(count, locale),
+            (_, Self::Minutes) => return Unit::Minutes.name(count, locale),
+            ("de", Self::Years) => ("Jahr", "Jahre"),
+            ("de", Self::Months) => ("Monat", "Monate"),
+            ("fr", Self::Years) => ("an", "ans"),
+            ("fr", Self::Months) => ("mois", "mois"),
+            ("es", Self::Years) => ("año", "años"),
+            ("es", Self::Months) => ("mes", "meses"),
+            ("it", Self::Years) => ("anno", "anni"),
+            ("it", Self::Months) => ("mese", "mesi"),
+            ("pt", Self::Years) => ("ano", "anos"),
+            ("pt", Self::Months) => ("mês", "meses"),
+            ("nl", Self::Years) => ("jaar", "jaar"),
+            ("nl", Self::Months) => ("maand", "maanden"),
+            ("en", Self::Years) => ("year", "years"),
+            ("en", Self::Months) => ("month", "months"),
+            _ => return None,
+        };
+
+        Some(if one { singular } else { plural })
+    }
+}
+
+/// Calendar difference from `since` to `until`, in the units from years down to `precision`
+///
+/// Whole months are counted first, clamping to the end of shorter months, so a month after January 31st is the end
+/// of February and someone born on February 29th turns one on February 28th. The rest is counted in exact days,
+/// hours and minutes, and the remainder below the precision is truncated. `None` if `since` is after `until`.
+pub(crate) fn breakdown(
+    since: DateTime<FixedOffset>,
+    until: DateTime<FixedOffset>,
+    precision: AgePrecision,
+) -> Option<Vec<(AgePrecision, u64)>> {
+    let since = since.with_timezone(&until.timezone());
+    if since > until {
+        return None;
+    }
+
+    let months = i64::from(until.year() - since.year()) * 12 + i64::from(until.month()) - i64::from(since.month());
+    let (months, anniversary) = [months, months - 1]
+        .into_iter()
+        .filter_map(|months| calendar::add_months(since, months, MonthOverflow::Clamp).map(|anniversary| (months, anniversary)))
+        .find(|(_months, anniversary)| *anniversary <= until)?;
+    let rest = until - anniversary;
+
+    let months = u64::try_from(months).ok()?;
+    let parts = AgePrecision::ALL
+        .into_iter()
+        .filter(|unit| *unit >= precision)
+        .map(|unit| {
+            let value = match unit {
+                AgePrecision::Years => months / 12,
+                AgePrecision::Months => months % 12,
+                AgePrecision::Days => rest.num_days().unsigned_abs(),
+                AgePrecision::Hours => (rest.num_hours() % 24).unsigned_abs(),
+                AgePrecision::Minutes => (rest.num_minutes() % 60).unsigned_abs(),
+            };
+
+            (unit, value)
+        })
+        .collect();
+
+    Some(parts)
+}
+
+/// Text of a breakdown, as in `38 years, 7 months, 2 days`, leaving out the units at zero
+///
+/// The units are joined with the separator, or as a list in the language of the locale without one. Fails for
+/// languages without bundled unit names.
+pub(crate) fn text(parts: &[(AgePrecision, u64)], separator: Option<&str>, locale: Option<&str>) -> Result<String, String> {
+    let name = |unit: AgePrecision, value| unit.name(value, locale).ok_or_else(|| duration::unsupported(locale));
+    let output = parts
+        .iter()
+        .filter(|(_unit, value)| *value > 0)
+        .map(|(unit, value)| Ok(format!("{} {}", value, name(*unit, *value)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    match (parts.last(), separator) {
+        (Some((smallest, _value)), _) if output.is_empty() => Ok(format!("0 {}", name(*smallest, 0)?)),
+        (_, Some(separator)) => Ok(output.join(separator)),
+        (_, None) => Ok(duration::join(&output, locale)),
+    }
+}
+
+/// JSON object of a breakdown, as in `{"days":2,"months":7,"years":38}`, units at zero included
+pub(crate) fn json(parts: &[(AgePrecision, u64)]) -> JsonValue {
+    let mut object = json!({});
+    for (unit, value) in parts {
+        object[unit.key()] = json!(value);
+    }
+
+    object
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn age(since: &str, until: &str, precision: AgePrecision) -> String {
+        let since = DateTime::parse_from_rfc3339(since).expect("Invalid since");
+        let until = DateTime::parse_from_rfc3339(until).expect("Invalid until");
+
+        text(&breakdown(since, until, precision).expect("Negative range"), None, None).expect("Unsupported locale")
+    }
+
+    #[test]
+    fn it_works() {
+        for (since, until, precision, comparison) in [
+            (
+                "1985-06-16T12:00:00Z",
+                "1989-08-09T09:30:11Z",
+                AgePrecision::Days,
+                "4 years, 1 month, 23 days",
+            ),
+            (
+                "1985-06-16T12:00:00Z",
+                "1989-08-09T09:30:11Z",
+                AgePrecision::Minutes,
+                "4 years, 1 month, 23 days, 21 hours, 30 minutes",
+            ),
+            (
+                "1985-06-16T12:00:00Z",
+                "1989-08-09T09:30:11Z",
+                AgePrecision::Months,
+                "4 years, 1 month",
+            ),
+            ("1985-06-16T12:00:00Z", "1989-08-09T09:30:11Z", AgePrecision::Years, "4 years"),
+            ("2024-01-31T00:00:00Z", "2024-02-29T00:00:00Z", AgePrecision::Days, "1 month"),
+            ("2023-01-31T00:00:00Z", "2023-03-01T00:00:00Z", AgePrecision::Days, "1 month, 1 day"),
+            ("2024-02-29T00:00:00Z", "2025-02-28T00:00:00Z", AgePrecision::Days, "1 year"),
+            (
+                "2024-02-29T00:00:00Z",
+                "2025-02-27T00:00:00Z",
+                AgePrecision::Days,
+                "11 months, 29 days",
+            ),
+            ("2024-02-29T00:00:00Z", "2028-02-29T00:00:00Z", AgePrecision::Days, "4 years"),
+            ("2024-03-31T10:00:00Z", "2024-04-30T09:00:00Z", AgePrecision::Days, "29 days"),
+            ("2024-03-31T10:00:00+02:00", "2024-04-30T12:00:00Z", AgePrecision::Days, "1 month"),
+            ("2024-01-01T00:00:00Z", "2024-01-01T00:00:59Z", AgePrecision::Minutes, "0 minutes"),
+        ] {
+            assert_eq!(age(since, until, precision), comparison, "Failed age from {} to {}", since, until);
+        }
+
+        let since = DateTime::parse_from_rfc3339("2021-02-01T00:00:00Z").expect("Invalid since");
+        let until = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").expect("Invalid until");
+        let parts = breakdown(since, until, AgePrecision::Months).expect("Negative range");
+
+        assert_eq!(text(&parts, Some(" "), None), Ok("3 years 4 months".to_string()));
+        assert_eq!(text(&parts, Some(" "), Some("de_DE")), Ok("3 Jahre 4 Monate".to_string()));
+        assert_eq!(text(&parts, None, Some("de_DE")), Ok("3 Jahre und 4 Monate".to_string()));
+        assert_eq!(json(&parts), json!({"years": 3, "months": 4}));
+    }
+
+    #[test]
+    fn it_craps() {
+        let since = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").expect("Invalid since");
+        let until = DateTime::parse_from_rfc3339("2024-05-31T23:59:59Z").expect("Invalid until");
+
+        assert_eq!(breakdown(since, until, AgePrecision::Days), None);
+        assert!(text(&[(AgePrecision::Years, 38)], None, Some("bg_BG")).is_err());
+        assert!("weeks".parse::<AgePrecision>().is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use handlebars::{BlockContext, BlockParams, Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext, Renderable};
+
+use crate::datetime::HandlebarsChronoDateTime;
+
+/// Renders the block once per item, as `this`, with `@index`, `@first` and `@last`, or the inverse block if there are none
+///
+/// Block params are supported too, as in `{{#each_date a b as |date i|}}`.
+pub(crate) fn render_each<'reg: 'rc, 'rc>(
+    items: Vec<JsonValue>,
+    h: &Helper<'rc>,
+    r: &'reg Handlebars,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    if items.is_empty() {
+        if let Some(inverse) = h.inverse() {
+            inverse.render(r, ctx, rc, out)?;
+        }
+
+        return Ok(());
+    }
+
+    let Some(template) = h.template() else {
+        return Ok(());
+    };
+
+    rc.push_block(BlockContext::new());
+
+    let len = items.len();
+    for (index, item) in items.into_iter().enumerate() {
+        if let Some(block) = rc.block_mut() {
+            block.set_local_var("index", JsonValue::from(index));
+            block.set_local_var("first", JsonValue::from(index == 0));
+            block.set_local_var("last", JsonValue::from(index == len - 1));
+
+            let mut params = BlockParams::new();
+            if let Some(name) = h.block_param() {
+                params.add_value(name, item.clone())?;
+            } else if let Some((name, index_name)) = h.block_param_pair() {
+                params.add_value(name, item.clone())?;
+                params.add_value(index_name, JsonValue::from(index))?;
+            }
+            block.set_block_params(params);
+
+            block.set_base_value(item);
+        }
+
+        template.render(r, ctx, rc, out)?;
+    }
+
+    rc.pop_block();
+
+    Ok(())
+}
+
+/// Renders the items of a block helper, or the fallback text of the helper in place of errors when not strict
+pub(crate) fn render_or_recover<'reg: 'rc, 'rc>(
+    helper: &HandlebarsChronoDateTime,
+    items: Result<Vec<JsonValue>, String>,
+    h: &Helper<'rc>,
+    r: &'reg Handlebars,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    match items {
+        Ok(items) => render_each(items, h, r, ctx, rc, out),
+        Err(e) => {
+            let fallback = helper.recover::<JsonValue>(Err(e))?;
+            out.write(fallback.as_str().unwrap_or_default())?;
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use chrono::{Datelike, Days, NaiveDate, Offset, Utc, Weekday};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::calendar::midnight;
+use crate::rrule::RRule;
+use crate::spec::Timezone;
+
+/// Source of public holidays used by the business-day modifiers and finalizers
+///
+/// Implement this trait to plug in any holiday data source, then register it on the helper with
+/// [`HandlebarsChronoDateTime::with_holiday_calendar`](crate::HandlebarsChronoDateTime::with_holiday_calendar).
+pub trait HolidayCalendar: Send + Sync {
+    /// Returns `true` if the given date is a holiday
+    fn is_holiday(&self, date: NaiveDate) -> bool;
+}
+
+/// In-memory holiday calendar
+///
+/// Holds both fixed dates (ex. `2024-05-06`) and dates recurring every year (ex. every 3rd of March).
+///
+/// ```rust
+/// use chrono::NaiveDate;
+/// use handlebars_chrono::{HolidayCalendar, MemoryHolidayCalendar};
+///
+/// let calendar = MemoryHolidayCalendar::new()
+///     .with_annual(3, 3)
+///     .with_date(NaiveDate::from_ymd_opt(2024, 5, 6).unwrap());
+///
+/// assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()));
+/// assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2024, 5, 6).unwrap()));
+/// assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 5, 6).unwrap()));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryHolidayCalendar {
+    dates: BTreeSet<NaiveDate>,
+    annual: BTreeSet<(u32, u32)>,
+    recurring: Vec<RecurringHoliday>,
+}
+
+/// Holiday of an iCalendar event repeated by a recurrence rule other than a plain `FREQ=YEARLY`
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RecurringHoliday {
+    rule: RRule,
+    start: NaiveDate,
+    /// Number of days of every occurrence, at least 1
+    days: u64,
+}
+
+impl RecurringHoliday {
+    /// Whether an occurrence of the event covers the given date
+    fn covers(&self, date: NaiveDate) -> bool {
+        let utc = Utc.fix();
+        let Some(from) = date.checked_sub_days(Days::new(self.days - 1)) else {
+            return false;
+        };
+        let (Some(start), Some(from_midnight)) = (midnight(utc, self.start), midnight(utc, from)) else {
+            return false;
+        };
+
+        self.rule
+            .occurrences(start, Timezone::Fixed(utc))
+            .skip_to(from_midnight)
+            .map(|occurrence| occurrence.date_naive())
+            .find(|day| *day >= from)
+            .is_some_and(|day| day <= date)
+    }
+}
+
+impl MemoryHolidayCalendar {
+    /// Empty holiday calendar
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a fixed date holiday
+    pub fn with_date(mut self, date: NaiveDate) -> Self {
+        self.insert_date(date);
+        self
+    }
+
+    /// Adds a holiday recurring every year on the given month and day of the month
+    pub fn with_annual(mut self, month: u32, day: u32) -> Self {
+        self.insert_annual(month, day);
+        self
+    }
+
+    /// Adds a fixed date holiday
+    pub fn insert_date(&mut self, date: NaiveDate) {
+        self.dates.insert(date);
+    }
+
+    /// Adds a holiday recurring every year on the given month and day of the month
+    pub fn insert_annual(&mut self, month: u32, day: u32) {
+        self.annual.insert((month, day));
+    }
+
+    /// Loads the holidays from the `VEVENT` entries of an iCalendar (RFC 5545) document
+    ///
+    /// Every day between `DTSTART` and `DTEND` (exclusive) is considered a holiday.
+    /// Events with a plain `RRULE:FREQ=YEARLY` are added as holidays recurring every year, any other recurrence
+    /// rule is expanded as an [`RRule`](crate::RRule), honoring `COUNT`, `UNTIL`, `INTERVAL` and the `BY*` parts.
+    pub fn from_ics(ics: &str) -> Result<Self, IcsError> {
+        let mut calendar = Self::new();
+
+        // unfold content lines, see RFC 5545 section 3.1
+        let mut lines: Vec<String> = Vec::new();
+        for line in ics.lines() {
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(continuation), Some(last)) => last.push_str(continuation),
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        let mut in_event = false;
+        let mut start: Option<NaiveDate> = None;
+        let mut end: Option<NaiveDate> = None;
+        let mut rule: Option<String> = None;
+
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let name = name.split(';').next().unwrap_or_default().to_ascii_uppercase();
+            let value = value.trim();
+
+            match (name.as_str(), value) {
+                ("BEGIN", "VEVENT") => {
+                    in_event = true;
+                    start = None;
+                    end = None;
+                    rule = None;
+                }
+                ("END", "VEVENT") if in_event => {
+                    in_event = false;
+
+                    let start = start.ok_or_else(|| IcsError("Event without DTSTART".to_string()))?;
+                    let end = end.unwrap_or(start + Days::new(1)).max(start + Days::new(1));
+
+                    let yearly = rule.as_deref().is_some_and(|rule| rule.eq_ignore_ascii_case("FREQ=YEARLY"));
+
+                    match rule.take() {
+                        Some(rule) if !yearly => calendar.recurring.push(RecurringHoliday {
+                            rule: rule
+                                .parse()
+                                .map_err(|e| IcsError(format!("Unsupported recurrence rule {}: {}", rule, e)))?,
+                            start,
+                            days: (end - start).num_days().unsigned_abs(),
+                        }),
+                        _ => {
+                            for date in start.iter_days().take_while(|date| *date < end) {
+                                if yearly {
+                                    calendar.insert_annual(date.month(), date.day());
+                                } else {
+                                    calendar.insert_date(date);
+                                }
+                            }
+                        }
+                    }
+                }
+                ("DTSTART", value) if in_event => start = Some(parse_ics_date(value)?),
+                ("DTEND", value) if in_event => end = Some(parse_ics_date(value)?),
+                ("RRULE", value) if in_event => rule = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(calendar)
+    }
+
+    /// Loads the holidays from an iCalendar (.ics) file, see [`MemoryHolidayCalendar::from_ics`]
+    pub fn from_ics_file<P: AsRef<Path>>(path: P) -> Result<Self, IcsError> {
+        let ics = std::fs::read_to_string(path).map_err(|e| IcsError(format!("Failed to read iCalendar file: {}", e)))?;
+
+        Self::from_ics(&ics)
+    }
+}
+
+impl HolidayCalendar for MemoryHolidayCalendar {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.dates.contains(&date)
+            || self.annual.contains(&(date.month(), date.day()))
+            || self.recurring.iter().any(|holiday| holiday.covers(date))
+    }
+}
+
+/// Error produced when loading an invalid iCalendar document
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IcsError(String);
+
+impl Display for IcsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for IcsError {}
+
+/// Parses an iCalendar `DATE` or `DATE-TIME` value, keeping only the date
+fn parse_ics_date(value: &str) -> Result<NaiveDate, IcsError> {
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .ok_or_else(|| IcsError(format!("Invalid iCalendar date: {}", value)))
+}
+
+/// Business day rules: the weekend days plus an optional holiday calendar
+pub(crate) struct BusinessDays<'a> {
+    pub weekend: &'a [Weekday],
+    pub holidays: Option<&'a dyn HolidayCalendar>,
+}
+
+impl BusinessDays<'_> {
+    /// Gives up after that many consecutive non-business days
+    const MAX_GAP: u32 = 3660;
+    /// Upper bound of calendar days walked, about a century, so a huge count can't render forever
+    pub const MAX_SPAN: u64 = 36_600;
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.contains(&date.weekday()) && !self.holidays.is_some_and(|holidays| holidays.is_holiday(date))
+    }
+
+    /// Number of calendar days to move from `date` in order to land `days` business days away
+    ///
+    /// `None` when no business day is found, or past [`BusinessDays::MAX_SPAN`] calendar days.
+    pub fn calendar_offset(&self, date: NaiveDate, days: i64) -> Option<i64> {
+        if days.unsigned_abs() > Self::MAX_SPAN {
+            return None;
+        }
+
+        let step = if days < 0 { -1 } else { 1 };
+        let mut remaining = days.unsigned_abs();
+        let mut offset: i64 = 0;
+        let mut gap = 0;
+
+        while remaining > 0 {
+            offset = offset.checked_add(step)?;
+            if offset.unsigned_abs() > Self::MAX_SPAN {
+                return None;
+            }
+
+            let current = if step < 0 {
+                date.checked_sub_days(Days::new(offset.unsigned_abs()))?
+            } else {
+                date.checked_add_days(Days::new(offset.unsigned_abs()))?
+            };
+
+            if self.is_business_day(current) {
+                remaining -= 1;
+                gap = 0;
+            } else {
+                gap += 1;
+                if gap > Self::MAX_GAP {
+                    return None;
+                }
+            }
+        }
+
+        Some(offset)
+    }
+
+    /// Number of business days after `from` up to and including `to`, negative if `to` is before `from`
+    ///
+    /// `None` when the dates are more than [`BusinessDays::MAX_SPAN`] calendar days apart.
+    pub fn between(&self, from: NaiveDate, to: NaiveDate) -> Option<i64> {
+        let (start, end, sign) = if to < from { (to, from, -1) } else { (from, to, 1) };
+        if (end - start).num_days().unsigned_abs() > Self::MAX_SPAN {
+            return None;
+        }
+
+        let count = start
+            .iter_days()
+            .skip(1)
+            .take_while(|date| *date <= end)
+            .filter(|date| self.is_business_day(*date))
+            .count() as i64;
+
+        Some(count * sign)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240101\r
+SUMMARY:New Year\r
+RRULE:FREQ=YEARLY\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240503\r
+DTEND;VALUE=DATE:20240507\r
+SUMMARY:Orthodox Easter\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20240924T000000Z\r
+SUMMARY:Independence\r
+  Day\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20241128\r
+DTEND;VALUE=DATE:20241130\r
+SUMMARY:Thanksgiving\r
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=4TH\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240102\r
+SUMMARY:Bridge day\r
+RRULE:FREQ=YEARLY;COUNT=2\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240701\r
+SUMMARY:Biennial retreat\r
+RRULE:FREQ=YEARLY;INTERVAL=2;UNTIL=20281231\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn it_works() {
+        let calendar = MemoryHolidayCalendar::from_ics(ICS).expect("Failed to parse iCalendar");
+
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2030, 1, 1).unwrap()));
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2024, 5, 3).unwrap()));
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2024, 5, 6).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2024, 5, 7).unwrap()));
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2024, 9, 24).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 9, 24).unwrap()));
+
+        // 4th Thursday of November and the Friday after
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 11, 27).unwrap()));
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 11, 28).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 11, 29).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 11, 26).unwrap()));
+        // COUNT=2
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2025, 1, 2).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2026, 1, 2).unwrap()));
+        // every other year until 2028
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2026, 7, 1).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2027, 7, 1).unwrap()));
+        assert!(calendar.is_holiday(NaiveDate::from_ymd_opt(2028, 7, 1).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2030, 7, 1).unwrap()));
+        assert!(!calendar.is_holiday(NaiveDate::from_ymd_opt(2022, 7, 1).unwrap()));
+
+        let business = BusinessDays {
+            weekend: &[Weekday::Sat, Weekday::Sun],
+            holidays: Some(&calendar),
+        };
+
+        // Thursday 2024-05-02 + 1 business day skips the Easter holidays and lands on Tuesday 2024-05-07
+        let thursday = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        assert_eq!(business.calendar_offset(thursday, 1), Some(5));
+        assert_eq!(business.calendar_offset(thursday, -1), Some(-1));
+        assert_eq!(business.calendar_offset(thursday, 0), Some(0));
+        assert_eq!(business.between(thursday, NaiveDate::from_ymd_opt(2024, 5, 10).unwrap()), Some(4));
+        assert_eq!(business.between(NaiveDate::from_ymd_opt(2024, 5, 10).unwrap(), thursday), Some(-4));
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!(MemoryHolidayCalendar::from_ics("BEGIN:VEVENT\nSUMMARY:Nothing\nEND:VEVENT\n").is_err());
+        assert!(MemoryHolidayCalendar::from_ics("BEGIN:VEVENT\nDTSTART:2024\nEND:VEVENT\n").is_err());
+        assert!(MemoryHolidayCalendar::from_ics("BEGIN:VEVENT\nDTSTART:20241128\nRRULE:FREQ=FORTNIGHTLY\nEND:VEVENT\n").is_err());
+        assert!(MemoryHolidayCalendar::from_ics("BEGIN:VEVENT\nDTSTART:20241128\nRRULE:FREQ=YEARLY;BYWEEKNO=1\nEND:VEVENT\n").is_err());
+        assert!(MemoryHolidayCalendar::from_ics_file("/nonexistent/holidays.ics").is_err());
+
+        let business = BusinessDays {
+            weekend: &[
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ],
+            holidays: None,
+        };
+        assert_eq!(business.calendar_offset(NaiveDate::from_ymd_opt(2024, 5, 2).unwrap(), 1), None);
+
+        let business = BusinessDays {
+            weekend: &[Weekday::Sat, Weekday::Sun],
+            holidays: None,
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        assert_eq!(business.calendar_offset(date, 100_000_000), None);
+        assert_eq!(business.calendar_offset(date, -30_000), None);
+        assert_eq!(business.between(date, NaiveDate::MAX), None);
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, TimeZone};
+use std::str::FromStr;
+
+/// What to do when adding months lands on a day that doesn't exist in the target month,
+/// as in Jan 31 + 1 month or Feb 29 + 1 year
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum MonthOverflow {
+    /// Clamp to the last day of the target month
+    #[default]
+    Clamp,
+    /// Fail instead of changing the day of the month
+    Error,
+}
+
+impl FromStr for MonthOverflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "clamp" => Ok(Self::Clamp),
+            "error" => Ok(Self::Error),
+            other => Err(format!("Expected `clamp` or `error`, got: {}", other)),
+        }
+    }
+}
+
+/// Adds a signed number of months, following the given overflow policy
+pub(crate) fn add_months(datetime: DateTime<FixedOffset>, months: i64, overflow: MonthOverflow) -> Option<DateTime<FixedOffset>> {
+    let shifted = if months < 0 {
+        datetime.checked_sub_months(Months::new(u32::try_from(months.unsigned_abs()).ok()?))?
+    } else {
+        datetime.checked_add_months(Months::new(u32::try_from(months).ok()?))?
+    };
+
+    if overflow == MonthOverflow::Error && shifted.day() != datetime.day() {
+        return None;
+    }
+
+    Some(shifted)
+}
+
+/// Adds a signed number of calendar days, keeping the local time
+pub(crate) fn add_days(datetime: DateTime<FixedOffset>, days: i64) -> Option<DateTime<FixedOffset>> {
+    if days < 0 {
+        datetime.checked_sub_days(Days::new(days.unsigned_abs()))
+    } else {
+        datetime.checked_add_days(Days::new(days.unsigned_abs()))
+    }
+}
+
+/// Start of a date in the given offset
+pub(crate) fn midnight(offset: FixedOffset, date: NaiveDate) -> Option<DateTime<FixedOffset>> {
+    offset.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let leap_day = DateTime::parse_from_rfc3339("2024-02-29T12:00:00+02:00").unwrap();
+
+        assert_eq!(
+            add_months(leap_day, 12, MonthOverflow::Clamp),
+            DateTime::parse_from_rfc3339("2025-02-28T12:00:00+02:00").ok()
+        );
+        assert_eq!(
+            add_months(leap_day, -12, MonthOverflow::Clamp),
+            DateTime::parse_from_rfc3339("2023-02-28T12:00:00+02:00").ok()
+        );
+        assert_eq!(
+            add_months(leap_day, 48, MonthOverflow::Error),
+            DateTime::parse_from_rfc3339("2028-02-29T12:00:00+02:00").ok()
+        );
+        assert_eq!(
+            add_months(leap_day, 3, MonthOverflow::Error),
+            DateTime::parse_from_rfc3339("2024-05-29T12:00:00+02:00").ok()
+        );
+
+        assert_eq!("Clamp".parse::<MonthOverflow>(), Ok(MonthOverflow::Clamp));
+        assert_eq!("error".parse::<MonthOverflow>(), Ok(MonthOverflow::Error));
+    }
+
+    #[test]
+    fn it_craps() {
+        let leap_day = DateTime::parse_from_rfc3339("2024-02-29T12:00:00+02:00").unwrap();
+
+        assert_eq!(add_months(leap_day, 12, MonthOverflow::Error), None);
+        assert_eq!(add_months(leap_day, i64::MAX, MonthOverflow::Clamp), None);
+        assert!("round".parse::<MonthOverflow>().is_err());
+    }
+}
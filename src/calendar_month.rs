@@ -0,0 +1,223 @@
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, JsonValue, Output, RenderContext};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::blocks::render_or_recover;
+use crate::calendar::midnight;
+use crate::companions::{instant, parts};
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::weekday::{parse_weekday, parse_weekdays, previous_or_same};
+
+/// `calendar_month` block helper, renders the block once with the weeks and days of a month, as in `{{#calendar_month event.start}}`
+///
+/// The month is the one of the given date time, of the `year` and `month` hash parameters, or the current one.
+/// The block gets `title`, `year`, `month`, `weekdays` (the column headers) and `weeks`, each with its `days`.
+/// Leading and trailing days of the adjacent months fill the first and the last week, flagged with `in_month=false`.
+#[derive(Clone)]
+pub(crate) struct CalendarMonth {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl CalendarMonth {
+    fn month(&self, h: &Helper) -> Result<JsonValue, String> {
+        let hash = |key| h.hash_get(key).map(|value| value.value().render());
+
+        let today = instant(&self.helper, None)?;
+        let reference = match (h.param(0), hash("year"), hash("month")) {
+            (Some(datetime), _, _) => instant(&self.helper, Some(datetime.value()))?,
+            (None, year, month) => {
+                let year = year
+                    .map(|year| year.parse().map_err(|e| format!("Invalid year parameter: {}", e)))
+                    .transpose()?
+                    .unwrap_or(today.year());
+                let month = month
+                    .map(|month| month.parse().map_err(|e| format!("Invalid month parameter: {}", e)))
+                    .transpose()?
+                    .unwrap_or(today.month());
+
+                NaiveDate::from_ymd_opt(year, month, 1)
+                    .and_then(|date| midnight(today.timezone(), date))
+                    .ok_or_else(|| "Year and month parameters produce invalid date".to_string())?
+            }
+        };
+
+        let first_weekday = hash("first_weekday")
+            .map(|day| parse_weekday(&day).map_err(|e| format!("Invalid first weekday parameter: {}", e)))
+            .transpose()?
+            .unwrap_or(self.helper.first_weekday());
+        let weekend = hash("weekend")
+            .map(|days| parse_weekdays(&days).map_err(|e| format!("Invalid weekend parameter: {}", e)))
+            .transpose()?
+            .unwrap_or_else(|| self.helper.weekend().to_vec());
+
+        let locale = hash("locale");
+        let locale = self.helper.locale(locale.as_deref());
+
+        let first = reference.date_naive().with_day(1).ok_or_else(|| "Invalid month".to_string())?;
+        let start = previous_or_same(first, first_weekday).ok_or_else(|| "Month out of range".to_string())?;
+
+        let day = |date: NaiveDate| -> Result<JsonValue, String> {
+            let datetime = midnight(reference.timezone(), date).ok_or_else(|| "Month out of range".to_string())?;
+
+            let mut day = parts(&datetime);
+            day["date"] = JsonValue::String(date.to_string());
+            day["in_month"] = JsonValue::Bool(date.month() == first.month());
+            day["is_today"] = JsonValue::Bool(date == today.date_naive());
+            day["is_weekend"] = JsonValue::Bool(weekend.contains(&date.weekday()));
+
+            Ok(day)
+        };
+
+        let mut weeks = Vec::new();
+        let mut week_start = start;
+        while week_start.year() < first.year() || (week_start.year() == first.year() && week_start.month() <= first.month()) {
+            let days = week_start.iter_days().take(7).map(day).collect::<Result<Vec<_>, _>>()?;
+            // the ISO week holding most days of the row, whatever the first weekday, is the one of its Thursday
+            let thursday = week_start
+                .iter_days()
+                .take(7)
+                .find(|date| date.weekday() == Weekday::Thu)
+                .unwrap_or(week_start);
+
+            weeks.push(json!({
+                "iso_week": thursday.iso_week().week(),
+                "days": days,
+            }));
+
+            week_start = week_start
+                .checked_add_days(Days::new(7))
+                .ok_or_else(|| "Month out of range".to_string())?;
+        }
+
+        let weekdays = start
+            .iter_days()
+            .take(7)
+            .map(|date| {
+                let datetime = midnight(reference.timezone(), date).ok_or_else(|| "Month out of range".to_string())?;
+
+                Ok(json!({
+                    "weekday": date.weekday().number_from_monday(),
+                    "short": self.helper.format(&datetime, "%a", locale)?,
+                    "long": self.helper.format(&datetime, "%A", locale)?,
+                    "is_weekend": weekend.contains(&date.weekday()),
+                }))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(json!({
+            "title": self.helper.format(&reference, "%B %Y", locale)?,
+            "year": first.year(),
+            "month": first.month(),
+            "weekdays": weekdays,
+            "weeks": weeks,
+        }))
+    }
+}
+
+impl HelperDef for CalendarMonth {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        render_or_recover(&self.helper, self.month(h).map(|month| vec![month]), h, r, ctx, rc, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+
+    #[test]
+    fn it_works() {
+        let h = registry();
+        let template = "{{#calendar_month date first_weekday=first_weekday}}{{title}}|{{#each weekdays}}{{short}} {{/each}}|{{#each weeks}}{{#each days}}{{#if in_month}}{{day}}{{else}}({{day}}){{/if}}{{#if is_weekend}}*{{/if}} {{/each}}/{{/each}}{{/calendar_month}}";
+
+        assert_eq!(
+            h.render_template(template, &json!({"date": 618658211, "first_weekday": "mon"}))
+                .expect("Render error"),
+            "August 1989|Mon Tue Wed Thu Fri Sat Sun |\
+             (31) 1 2 3 4 5* 6* /\
+             7 8 9 10 11 12* 13* /\
+             14 15 16 17 18 19* 20* /\
+             21 22 23 24 25 26* 27* /\
+             28 29 30 31 (1) (2)* (3)* /",
+            "Failed to render August 1989 starting on Monday"
+        );
+
+        assert_eq!(
+            h.render_template(template, &json!({"date": "2015-02-10T00:00:00Z", "first_weekday": "sun"}))
+                .expect("Render error"),
+            "February 2015|Sun Mon Tue Wed Thu Fri Sat |\
+             1* 2 3 4 5 6 7* /\
+             8* 9 10 11 12 13 14* /\
+             15* 16 17 18 19 20 21* /\
+             22* 23 24 25 26 27 28* /",
+            "Failed to render February 2015 starting on Sunday"
+        );
+
+        assert_eq!(
+            h.render_template(
+                "{{#calendar_month date first_weekday=first_weekday}}{{#each weeks}}{{iso_week}} {{/each}}{{/calendar_month}}",
+                &json!({"date": "2015-02-10T00:00:00Z", "first_weekday": "sun"})
+            )
+            .expect("Render error"),
+            "6 7 8 9 ",
+            "Failed to number the weeks starting on Sunday by their Thursday"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{#calendar_month year=1989 month=12 as |cal|}}{{cal.month}}/{{cal.year}} {{#each cal.weeks}}{{iso_week}} {{/each}}{{/calendar_month}}"#,
+                &json!({})
+            )
+            .expect("Render error"),
+            "12/1989 48 49 50 51 52 ",
+            "Failed to render December 1989 by year and month"
+        );
+
+        assert_eq!(
+            h.render_template(
+                "{{#calendar_month}}{{#each weeks}}{{#each days}}{{#if is_today}}today{{/if}}{{/each}}{{/each}}{{/calendar_month}}",
+                &json!({})
+            )
+            .expect("Render error"),
+            "today",
+            "Failed to flag today in the current month"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{#calendar_month 618658211 locale="de_DE"}}{{title}}: {{#each weekdays}}{{short}} {{/each}}{{/calendar_month}}"#,
+                &json!({})
+            )
+            .expect("Render error"),
+            "August 1989: Mo Di Mi Do Fr Sa So ",
+            "Failed to render localized weekday headers"
+        );
+    }
+
+    #[test]
+    fn it_craps() {
+        let h = registry();
+
+        for template in [
+            r#"{{#calendar_month "yesterday"}}{{/calendar_month}}"#,
+            r#"{{#calendar_month year=1989 month=13}}{{/calendar_month}}"#,
+            r#"{{#calendar_month 618658211 first_weekday="someday"}}{{/calendar_month}}"#,
+            r#"{{#calendar_month 618658211 weekend="sat,someday"}}{{/calendar_month}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &json!({})), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::styles::{self, Style};
+
+/// Strftime patterns of the `to_calendar` finalizer, one per distance from the base day
+///
+/// Missing patterns fall back to the ones of the locale, as in `Today at 9:30 AM` or `Last Monday at 9:30 AM`, or to
+/// its full date and short time for languages without bundled words.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CalendarFormats {
+    /// Same day as the base, `calendar_same_day`
+    pub same_day: Option<String>,
+    /// Day after the base, `calendar_next_day`
+    pub next_day: Option<String>,
+    /// Day before the base, `calendar_last_day`
+    pub last_day: Option<String>,
+    /// Two to six days after the base, `calendar_next_week`
+    pub next_week: Option<String>,
+    /// Two to six days before the base, `calendar_last_week`
+    pub last_week: Option<String>,
+    /// Any other day, `calendar_same_else`
+    pub same_else: Option<String>,
+}
+
+/// Distance of a day from the base day, in the manner of Moment.js `calendar()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Bucket {
+    SameDay,
+    NextDay,
+    LastDay,
+    NextWeek,
+    LastWeek,
+    SameElse,
+}
+
+impl Bucket {
+    fn of(datetime: &DateTime<FixedOffset>, base: &DateTime<FixedOffset>) -> Self {
+        let base = base.with_timezone(&datetime.timezone());
+
+        match (datetime.date_naive() - base.date_naive()).num_days() {
+            0 => Self::SameDay,
+            1 => Self::NextDay,
+            -1 => Self::LastDay,
+            2..=6 => Self::NextWeek,
+            -6..=-2 => Self::LastWeek,
+            _ => Self::SameElse,
+        }
+    }
+
+    /// Words before the time in the language of the locale, `None` for languages without bundled words
+    fn prefix(self, locale: &str) -> Option<&'static str> {
+        let prefix = match (styles::language(Some(locale)), self) {
+            ("de", Self::SameDay) => "Heute um ",
+            ("de", Self::NextDay) => "Morgen um ",
+            ("de", Self::LastDay) => "Gestern um ",
+            ("de", Self::NextWeek) => "%A um ",
+            ("de", Self::LastWeek) => "Letzten %A um ",
+            ("fr", Self::SameDay) => "Aujourd'hui à ",
+            ("fr", Self::NextDay) => "Demain à ",
+            ("fr", Self::LastDay) => "Hier à ",
+            ("fr", Self::NextWeek) => "%A à ",
+            ("fr", Self::LastWeek) => "%A dernier à ",
+            ("es", Self::SameDay) => "Hoy a las ",
+            ("es", Self::NextDay) => "Mañana a las ",
+            ("es", Self::LastDay) => "Ayer a las ",
+            ("es", Self::NextWeek) => "%A a las ",
+            ("es", Self::LastWeek) => "El %A pasado a las ",
+            ("it", Self::SameDay) => "Oggi alle ",
+            ("it", Self::NextDay) => "Domani alle ",
+            ("it", Self::LastDay) => "Ieri alle ",
+            ("it", Self::NextWeek) => "%A alle ",
+            ("it", Self::LastWeek) => "%A della settimana scorsa alle ",
+            ("pt", Self::SameDay) => "Hoje às ",
+            ("pt", Self::NextDay) => "Amanhã às ",
+            ("pt", Self::LastDay) => "Ontem às ",
+            ("pt", Self::NextWeek) => "%A às ",
+            ("pt", Self::LastWeek) => "%A da semana passada às ",
+            ("nl", Self::SameDay) => "Vandaag om ",
+            ("nl", Self::NextDay) => "Morgen om ",
+            ("nl", Self::LastDay) => "Gisteren om ",
+            ("nl", Self::NextWeek) => "%A om ",
+            ("nl", Self::LastWeek) => "Afgelopen %A om ",
+            ("en", Self::SameDay) => "Today at ",
+            ("en", Self::NextDay) => "Tomorrow at ",
+            ("en", Self::LastDay) => "Yesterday at ",
+            ("en", Self::NextWeek) => "Next %A at ",
+            ("en", Self::LastWeek) => "Last %A at ",
+            _ => return None,
+        };
+
+        Some(prefix)
+    }
+}
+
+impl CalendarFormats {
+    /// Strftime pattern of the date time, given its distance from the base
+    pub(crate) fn pattern(&self, datetime: &DateTime<FixedOffset>, base: &DateTime<FixedOffset>, locale: Option<&str>) -> String {
+        let bucket = Bucket::of(datetime, base);
+        let format = match bucket {
+            Bucket::SameDay => &self.same_day,
+            Bucket::NextDay => &self.next_day,
+            Bucket::LastDay => &self.last_day,
+            Bucket::NextWeek => &self.next_week,
+            Bucket::LastWeek => &self.last_week,
+            Bucket::SameElse => &self.same_else,
+        };
+
+        if let Some(format) = format {
+            return format.clone();
+        }
+
+        let locale = locale.unwrap_or("en_US");
+        match (bucket, bucket.prefix(locale)) {
+            (Bucket::SameElse, _) => styles::pattern(locale, Some(Style::Long), None),
+            (_, Some(prefix)) => format!("{}{}", prefix, styles::pattern(locale, None, Some(Style::Short))),
+            // no words to name the day with, the full date of the locale rather than English words
+            (_, None) => styles::pattern(locale, Some(Style::Full), Some(Style::Short)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let base = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00").expect("Invalid base");
+        let formats = CalendarFormats::default();
+
+        for (datetime, comparison) in [
+            ("1989-08-09T23:00:00+02:00", "Today at %-I:%M %p"),
+            ("1989-08-09T00:00:00+02:00", "Today at %-I:%M %p"),
+            ("1989-08-10T00:00:00+02:00", "Tomorrow at %-I:%M %p"),
+            ("1989-08-08T23:59:59+02:00", "Yesterday at %-I:%M %p"),
+            ("1989-08-15T12:00:00+02:00", "Next %A at %-I:%M %p"),
+            ("1989-08-03T12:00:00+02:00", "Last %A at %-I:%M %p"),
+            ("1989-08-16T12:00:00+02:00", "%B %-d, %Y"),
+            ("1989-08-02T12:00:00+02:00", "%B %-d, %Y"),
+            // the base day is taken in the offset of the date time
+            ("1989-08-09T23:00:00-05:00", "Today at %-I:%M %p"),
+        ] {
+            let datetime = DateTime::parse_from_rfc3339(datetime).expect("Invalid date time");
+            assert_eq!(
+                formats.pattern(&datetime, &base, None),
+                comparison,
+                "Failed to pick pattern of {}",
+                datetime
+            );
+        }
+
+        let datetime = DateTime::parse_from_rfc3339("1989-08-07T12:00:00+02:00").expect("Invalid date time");
+        assert_eq!(formats.pattern(&datetime, &base, Some("de_DE")), "Letzten %A um %H:%M");
+        assert_eq!(formats.pattern(&datetime, &base, Some("fr_FR")), "%A dernier à %H:%M");
+        assert_eq!(formats.pattern(&base, &base, Some("bg_BG")), "%A, %-d %B %Y г., %-H:%M ч.");
+        assert_eq!(formats.pattern(&datetime, &base, Some("bg_BG")), "%A, %-d %B %Y г., %-H:%M ч.");
+
+        let formats = CalendarFormats {
+            last_week: Some("%a".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(formats.pattern(&datetime, &base, Some("de_DE")), "%a");
+        assert_eq!(formats.pattern(&base, &base, None), "Today at %-I:%M %p");
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, FixedOffset};
+use handlebars::{Context, Handlebars, Helper, HelperDef, JsonRender, JsonValue, RenderContext, RenderError, ScopedJson};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::calendar::MonthOverflow;
+use crate::companions::instant;
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::each_date::Step;
+
+/// An item of an array together with its date time
+pub(crate) type Dated = (DateTime<FixedOffset>, JsonValue);
+
+/// Value at a dotted path of an item, as in `meta.published` or `dates.0`, the item itself for an empty path
+pub(crate) fn field<'a>(item: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(item, |value, key| match value {
+            JsonValue::Object(map) => map.get(key),
+            JsonValue::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None,
+        })
+}
+
+/// Items of the array in the first parameter of a helper, each with the date time at the path in the second parameter
+///
+/// The date times are UNIX timestamps in seconds, RFC3339 or RFC2822 date times, so mixed fields work.
+pub(crate) fn dated(helper: &HandlebarsChronoDateTime, h: &Helper) -> Result<Vec<Dated>, String> {
+    let items = match h.param(0).map(|items| items.value()) {
+        Some(JsonValue::Array(items)) => items,
+        Some(JsonValue::Null) => return Ok(Vec::new()),
+        Some(value) => return Err(format!("Expected an array as first parameter of `{}`, got {}", h.name(), value)),
+        None => return Err(format!("Missing array parameter of `{}`", h.name())),
+    };
+    let path = h.param(1).map(|path| path.value().render()).unwrap_or_default();
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| match field(item, &path) {
+            Some(value) if !value.is_null() => instant(helper, Some(value))
+                .map(|datetime| (datetime, item.clone()))
+                .map_err(|e| format!("Invalid date time of item {}: {}", index, e)),
+            _ => Err(format!("Missing date time of item {} at `{}`", index, path)),
+        })
+        .collect()
+}
+
+/// Start and end of a window, open ended when missing
+pub(crate) type Bounds = (Option<DateTime<FixedOffset>>, Option<DateTime<FixedOffset>>);
+
+/// Span of time relative to now, as in `last 30 days`, `next week`, `past` or `upcoming`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Window {
+    Last(u32, Step),
+    Next(u32, Step),
+    Past,
+    Upcoming,
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().map(|word| word.to_lowercase()).collect::<Vec<_>>();
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let span = |count: &str, unit: &str| -> Result<(u32, Step), String> {
+            Ok((
+                count.parse().map_err(|e| format!("Invalid window parameter: {}: {}", s, e))?,
+                unit.parse().map_err(|e| format!("Invalid window parameter: {}: {}", s, e))?,
+            ))
+        };
+
+        match words.as_slice() {
+            ["past"] => Ok(Self::Past),
+            ["upcoming" | "future"] => Ok(Self::Upcoming),
+            ["last", unit] => span("1", unit).map(|(count, step)| Self::Last(count, step)),
+            ["next", unit] => span("1", unit).map(|(count, step)| Self::Next(count, step)),
+            ["last", count, unit] => span(count, unit).map(|(count, step)| Self::Last(count, step)),
+            ["next", count, unit] => span(count, unit).map(|(count, step)| Self::Next(count, step)),
+            _ => Err(format!(
+                "Invalid window parameter: {}. Expected last or next followed by a count and a unit, past or upcoming",
+                s
+            )),
+        }
+    }
+}
+
+impl Window {
+    /// Bounds of the window around `now`
+    pub fn bounds(&self, now: DateTime<FixedOffset>) -> Result<Bounds, String> {
+        let shift = |count: u32, step: &Step, sign: i64| {
+            step.0
+                .scaled(i64::from(count) * sign)
+                .and_then(|duration| duration.add_to(now, MonthOverflow::Clamp))
+                .ok_or_else(|| "Window out of range".to_string())
+        };
+
+        Ok(match self {
+            Self::Last(count, step) => (Some(shift(*count, step, -1)?), Some(now)),
+            Self::Next(count, step) => (Some(now), Some(shift(*count, step, 1)?)),
+            Self::Past => (None, Some(now)),
+            Self::Upcoming => (Some(now), None),
+        })
+    }
+}
+
+/// Operation of a [`Collection`] helper
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Sort,
+    Filter,
+    Earliest,
+    Latest,
+}
+
+/// `sort_by_date`, `filter_by_date`, `earliest` and `latest` helpers, as in `{{#each (sort_by_date events "start")}}`
+///
+/// The first parameter is an array, the second one the dotted path of the date time of each item. `sort_by_date`
+/// orders the items oldest first unless `order="desc"`, `filter_by_date` keeps the items within `from` and `to`,
+/// both included, or within a `window` around now or `base`, and `earliest` and `latest` pick a single item.
+#[derive(Clone)]
+pub(crate) struct Collection {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+    pub operation: Operation,
+}
+
+impl Collection {
+    fn apply(&self, h: &Helper) -> Result<JsonValue, String> {
+        let mut items = dated(&self.helper, h)?;
+
+        match self.operation {
+            Operation::Sort => {
+                let descending = match h.hash_get("order").map(|order| order.value().render()).as_deref() {
+                    Some("asc") | None => false,
+                    Some("desc") => true,
+                    Some(order) => return Err(format!("Invalid order parameter: {}. Supported values are asc and desc", order)),
+                };
+
+                items.sort_by(|(left, _), (right, _)| if descending { right.cmp(left) } else { left.cmp(right) });
+            }
+            Operation::Filter => {
+                let bound = |key| h.hash_get(key).map(|value| instant(&self.helper, Some(value.value()))).transpose();
+
+                let (from, to) = match h.hash_get("window").map(|window| window.value().render()) {
+                    Some(window) => {
+                        let now = instant(&self.helper, h.hash_get("base").map(|base| base.value()))?;
+                        window.parse::<Window>()?.bounds(now)?
+                    }
+                    None => (bound("from")?, bound("to")?),
+                };
+
+                items.retain(|(datetime, _)| from.is_none_or(|from| *datetime >= from) && to.is_none_or(|to| *datetime <= to));
+            }
+            Operation::Earliest | Operation::Latest => {
+                let item = if self.operation == Operation::Earliest {
+                    items.into_iter().min_by_key(|(datetime, _)| *datetime)
+                } else {
+                    items.into_iter().max_by_key(|(datetime, _)| *datetime)
+                };
+
+                return Ok(item.map(|(_, item)| item).unwrap_or_default());
+            }
+        }
+
+        Ok(JsonValue::Array(items.into_iter().map(|(_, item)| item).collect()))
+    }
+}
+
+impl HelperDef for Collection {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let fallback = match self.operation {
+            Operation::Sort | Operation::Filter => JsonValue::Array(Vec::new()),
+            Operation::Earliest | Operation::Latest => JsonValue::Null,
+        };
+
+        Ok(self.helper.recover_or(self.apply(h), fallback)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+    use serde_json::json;
+
+    #[test]
+    fn it_works() {
+        let item = json!({"title": "Hello", "meta": {"published": 618658211, "dates": ["1989-08-09T09:30:11Z"]}});
+
+        assert_eq!(field(&item, "meta.published"), Some(&json!(618658211)));
+        assert_eq!(field(&item, "meta.dates.0"), Some(&json!("1989-08-09T09:30:11Z")));
+        assert_eq!(field(&item, ""), Some(&item));
+
+        let h = registry();
+        let data = json!({
+            "events": [
+                {"title": "a", "start": 618658211},
+                {"title": "b", "start": "1989-08-20T10:00:00Z"},
+                {"title": "c", "start": "618000000"},
+                {"title": "d", "start": "Sat, 02 Sep 1989 10:00:00 +0000"},
+            ],
+            "none": [],
+        });
+
+        for (template, comparison) in [
+            (r#"{{#each (sort_by_date events "start")}}{{title}}{{/each}}"#, "cabd"),
+            (r#"{{#each (sort_by_date events "start" order="desc")}}{{title}}{{/each}}"#, "dbac"),
+            (
+                r#"{{#each (filter_by_date events "start" from=618658211 to="1989-08-31T00:00:00Z")}}{{title}}{{/each}}"#,
+                "ab",
+            ),
+            (r#"{{#each (filter_by_date events "start" to=618658211)}}{{title}}{{/each}}"#, "ac"),
+            (
+                r#"{{#each (filter_by_date events "start" window="last 10 days" base="1989-08-21T00:00:00Z")}}{{title}}{{/each}}"#,
+                "b",
+            ),
+            (
+                r#"{{#each (filter_by_date events "start" window="next month" base=618658211)}}{{title}}{{/each}}"#,
+                "abd",
+            ),
+            (
+                r#"{{#each (sort_by_date (filter_by_date events "start" window="upcoming" base="1989-08-05T00:00:00Z") "start")}}{{title}}{{/each}}"#,
+                "abd",
+            ),
+            (
+                r#"{{#each (filter_by_date events "start" window="past")}}{{title}}{{/each}}"#,
+                "abcd",
+            ),
+            (r#"{{#with (earliest events "start")}}{{title}}{{/with}}"#, "c"),
+            (r#"{{#with (latest events "start")}}{{title}}{{/with}}"#, "d"),
+            (r#"{{#with (latest none "start")}}{{title}}{{else}}none{{/with}}"#, "none"),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn it_craps() {
+        let item = json!({"title": "Hello", "meta": {"dates": []}});
+
+        assert_eq!(field(&item, "meta.published"), None);
+        assert_eq!(field(&item, "meta.dates.0"), None);
+        assert_eq!(field(&item, "title.length"), None);
+
+        let h = registry();
+        let data = json!({"events": [{"title": "a", "start": 618658211}, {"title": "b"}], "single": [{"start": 618658211}]});
+
+        for template in [
+            "{{sort_by_date}}",
+            r#"{{sort_by_date "events" "start"}}"#,
+            r#"{{sort_by_date events "start"}}"#,
+            r#"{{sort_by_date single "start" order="random"}}"#,
+            r#"{{filter_by_date single "start" from="yesterday"}}"#,
+            r#"{{filter_by_date single "start" window="last fortnight"}}"#,
+            r#"{{filter_by_date single "start" window="last -3 days"}}"#,
+            r#"{{filter_by_date single "start" window="recent"}}"#,
+            r#"{{earliest single "title"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &data), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+
+        let helper = Arc::new(HandlebarsChronoDateTime::default().with_strict(false).with_fallback("n/a"));
+        let mut h = Handlebars::new();
+        for (name, operation) in [("filter_by_date", Operation::Filter), ("latest", Operation::Latest)] {
+            h.register_helper(
+                name,
+                Box::new(Collection {
+                    helper: helper.clone(),
+                    operation,
+                }),
+            );
+        }
+
+        for (template, comparison) in [
+            (r#"{{#each (filter_by_date events "start")}}{{title}}{{else}}none{{/each}}"#, "none"),
+            (r#"{{#with (latest events "start")}}{{title}}{{else}}none{{/with}}"#, "none"),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to fall back to an empty value with {}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use chrono::{DateTime, Datelike, FixedOffset, Timelike};
+use handlebars::{Context, Handlebars, Helper, HelperDef, JsonRender, JsonValue, RenderContext, RenderError, ScopedJson};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::spec::{DateTimeSpec, Initializer};
+
+/// Order checked by a [`Compare`] helper
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Before,
+    After,
+    Same,
+}
+
+/// `before`, `after` and `same` helpers, as in `{{#if (before event.start)}}`
+///
+/// Both parameters are UNIX timestamps in seconds, RFC3339 or RFC2822 date times, the second one defaults to now.
+/// `same` compares the exact instants, or the calendar fields up to the `unit` hash parameter, as in `unit="day"`.
+#[derive(Clone)]
+pub(crate) struct Compare {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+    pub comparison: Comparison,
+}
+
+impl HelperDef for Compare {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let result = operands(&self.helper, h).and_then(|(left, right)| match self.comparison {
+            Comparison::Before => Ok(left < right),
+            Comparison::After => Ok(left > right),
+            Comparison::Same => match h.hash_get("unit").map(|unit| unit.value().render()) {
+                Some(unit) => same(left, right, &unit),
+                None => Ok(left == right),
+            },
+        });
+
+        Ok(self.helper.recover_or(result, JsonValue::Bool(false))?.into())
+    }
+}
+
+/// `diff` helper, the difference between two date times in whole `unit`s, as in `{{diff start end unit="days"}}`
+///
+/// Positive if the second date time is later, the unit defaults to `seconds`.
+#[derive(Clone)]
+pub(crate) struct Diff {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl HelperDef for Diff {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let result = operands(&self.helper, h).and_then(|(left, right)| {
+            let delta = right - left;
+
+            match h.hash_get("unit").map(|unit| unit.value().render()).as_deref() {
+                Some("weeks") => Ok(delta.num_weeks()),
+                Some("days") => Ok(delta.num_days()),
+                Some("hours") => Ok(delta.num_hours()),
+                Some("minutes") => Ok(delta.num_minutes()),
+                Some("seconds") | None => Ok(delta.num_seconds()),
+                Some("milliseconds") => Ok(delta.num_milliseconds()),
+                Some(unit) => Err(format!("Invalid unit parameter: {}", unit)),
+            }
+        });
+
+        Ok(self.helper.recover_or(result, JsonValue::Null)?.into())
+    }
+}
+
+/// Evaluates a date time parameter of a companion helper, in the default timezone of the helper
+pub(crate) fn instant(helper: &HandlebarsChronoDateTime, value: Option<&JsonValue>) -> Result<DateTime<FixedOffset>, String> {
+    let initializer = match value {
+        Some(value) => Initializer::from_value(value)?,
+        None => Initializer::Now,
+    };
+
+    helper.evaluate(&DateTimeSpec::new().with_initializer(initializer))
+}
+
+/// Fields of a date time, for templates to pick from, as in `{{this.year}}` or `{{this.weekday}}`
+pub(crate) fn parts(datetime: &DateTime<FixedOffset>) -> JsonValue {
+    json!({
+        "rfc3339": datetime.to_rfc3339(),
+        "timestamp": datetime.timestamp(),
+        "year": datetime.year(),
+        "quarter": datetime.month0() / 3 + 1,
+        "month": datetime.month(),
+        "day": datetime.day(),
+        "hour": datetime.hour(),
+        "minute": datetime.minute(),
+        "second": datetime.second(),
+        "weekday": datetime.weekday().number_from_monday(),
+        "ordinal": datetime.ordinal(),
+        "iso_week": datetime.iso_week().week(),
+        "offset": datetime.offset().to_string(),
+    })
+}
+
+/// The first two parameters of a helper, the second one defaulting to now
+fn operands(helper: &HandlebarsChronoDateTime, h: &Helper) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), String> {
+    let left = h.param(0).ok_or_else(|| format!("Missing date time parameter of `{}`", h.name()))?;
+
+    Ok((
+        instant(helper, Some(left.value()))?,
+        instant(helper, h.param(1).map(|right| right.value()))?,
+    ))
+}
+
+/// Compares the calendar fields of two date times, from the year down to the given unit
+fn same(left: DateTime<FixedOffset>, right: DateTime<FixedOffset>, unit: &str) -> Result<bool, String> {
+    let same_day = left.date_naive() == right.date_naive();
+
+    match unit {
+        "year" => Ok(left.year() == right.year()),
+        "quarter" => Ok(left.year() == right.year() && left.month0() / 3 == right.month0() / 3),
+        "month" => Ok(left.year() == right.year() && left.month() == right.month()),
+        "week" => Ok(left.iso_week() == right.iso_week()),
+        "day" => Ok(same_day),
+        "hour" => Ok(same_day && left.hour() == right.hour()),
+        "minute" => Ok(same_day && (left.hour(), left.minute()) == (right.hour(), right.minute())),
+        "second" => Ok(left.timestamp() == right.timestamp()),
+        unit => Err(format!("Invalid unit parameter: {}", unit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+    use serde_json::json;
+
+    #[test]
+    fn it_works() {
+        let h = registry();
+        let data = json!({
+            "start": 618658211,
+            "end": "1989-08-11T11:30:11+02:00",
+            "rfc2822": "Wed, 09 Aug 1989 11:30:11 +0200",
+        });
+
+        for (template, comparison) in [
+            ("{{before start end}}", "true"),
+            ("{{after start end}}", "false"),
+            ("{{before start}}", "true"),
+            ("{{after end}}", "false"),
+            ("{{same start rfc2822}}", "true"),
+            (r#"{{same start end unit="month"}}"#, "true"),
+            (r#"{{same start end unit="day"}}"#, "false"),
+            ("{{#if (before start end)}}yes{{else}}no{{/if}}", "yes"),
+            ("{{diff start end}}", "172800"),
+            (r#"{{diff end start unit="days"}}"#, "-2"),
+            (r#"{{diff start "1989-08-10T09:30:10Z" unit="days"}}"#, "0"),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn it_craps() {
+        let h = registry();
+
+        for template in [
+            "{{before}}",
+            r#"{{before "yesterday"}}"#,
+            r#"{{same 0 1 unit="decade"}}"#,
+            r#"{{diff 0 1 unit="fortnights"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &json!({})), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+
+        let helper = Arc::new(HandlebarsChronoDateTime::default().with_strict(false).with_fallback("n/a"));
+        let mut h = Handlebars::new();
+        h.register_helper(
+            "before",
+            Box::new(Compare {
+                helper: helper.clone(),
+                comparison: Comparison::Before,
+            }),
+        );
+        h.register_helper("diff", Box::new(Diff { helper }));
+
+        for (template, comparison) in [
+            (r#"{{#if (before "yesterday" 0)}}yes{{else}}no{{/if}}"#, "no"),
+            (r#"{{#if (diff "yesterday" 0)}}yes{{else}}no{{/if}}"#, "no"),
+        ] {
+            assert_eq!(
+                h.render_template(template, &json!({})).expect("Render error"),
+                comparison,
+                "Failed to fall back to a falsy value with {}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::spec::Timezone;
+use crate::weekday::parse_weekday;
+
+/// Helper defaults, typically deserialized from a section of the application config
+///
+/// Every field is optional, missing fields keep the defaults of [`HandlebarsChronoDateTime::new`].
+///
+/// ```rust
+/// use handlebars::Handlebars;
+/// use handlebars_chrono::HelperConfig;
+/// use serde_json::json;
+///
+/// let config: HelperConfig = serde_json::from_value(json!({
+///     "timezone": "+02:00",
+///     "formats": { "invoice": "%d.%m.%Y %H:%M" },
+///     "strict": false,
+///     "fallback": "n/a",
+/// }))
+/// .expect("Invalid config");
+///
+/// let mut h = Handlebars::new();
+/// h.register_helper("datetime", Box::new(config.build().expect("Invalid config")));
+///
+/// assert_eq!(h.render_template(r#"{{datetime from_timestamp="618658211" format="invoice"}}"#, &json!({})).expect("Render error"), "09.08.1989 11:30");
+/// assert_eq!(h.render_template(r#"{{datetime from_timestamp="yesterday"}}"#, &json!({})).expect("Render error"), "n/a");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HelperConfig {
+    /// Default timezone: `local`, a fixed offset like `+02:00` or an IANA timezone name with the `timezone` feature
+    pub timezone: Option<String>,
+    /// Default locale of the formatting finalizers, requires the `locale` feature
+    pub locale: Option<String>,
+    /// Named strftime output formats, selected with the `format` hash parameter
+    pub formats: BTreeMap<String, String>,
+    /// Default syntax of the `output_format` hash parameter
+    pub format_syntax: Option<String>,
+    /// Weekend days skipped by the business-day modifiers and finalizers
+    pub weekend: Option<Vec<String>>,
+    /// First day of the week of `calendar_month` and of weekly groups
+    pub first_weekday: Option<String>,
+    /// Invalid parameters and dates fail the render when `true` (the default), or render `fallback` otherwise
+    pub strict: bool,
+    /// Text rendered in place of errors when not strict
+    pub fallback: Option<String>,
+}
+
+impl Default for HelperConfig {
+    fn default() -> Self {
+        Self {
+            timezone: None,
+            locale: None,
+            formats: BTreeMap::new(),
+            format_syntax: None,
+            weekend: None,
+            first_weekday: None,
+            strict: true,
+            fallback: None,
+        }
+    }
+}
+
+impl HelperConfig {
+    /// Builds a helper with these defaults, failing on invalid values instead of at render time
+    pub fn build(&self) -> Result<HandlebarsChronoDateTime, ConfigError> {
+        self.apply(HandlebarsChronoDateTime::new())
+    }
+
+    /// Applies these defaults on top of an existing helper, as in one with a holiday calendar
+    pub fn apply(&self, helper: HandlebarsChronoDateTime) -> Result<HandlebarsChronoDateTime, ConfigError> {
+        let helper = if let Some(timezone) = &self.timezone {
+            helper.with_timezone(timezone.parse::<Timezone>().map_err(ConfigError::invalid("timezone"))?)
+        } else {
+            helper
+        };
+
+        let helper = if let Some(locale) = &self.locale {
+            helper.with_locale(validate_locale(locale)?)
+        } else {
+            helper
+        };
+
+        let helper = if let Some(format_syntax) = &self.format_syntax {
+            helper.with_format_syntax(format_syntax.parse().map_err(ConfigError::invalid("format syntax"))?)
+        } else {
+            helper
+        };
+
+        let helper = if let Some(weekend) = &self.weekend {
+            let weekend = weekend
+                .iter()
+                .map(|day| parse_weekday(day))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(ConfigError::invalid("weekend"))?;
+
+            helper.with_weekend(&weekend)
+        } else {
+            helper
+        };
+
+        let helper = if let Some(first_weekday) = &self.first_weekday {
+            helper.with_first_weekday(parse_weekday(first_weekday).map_err(ConfigError::invalid("first weekday"))?)
+        } else {
+            helper
+        };
+
+        let helper = if let Some(fallback) = &self.fallback {
+            helper.with_fallback(fallback)
+        } else {
+            helper
+        };
+
+        let helper = self.formats.iter().try_fold(helper, |helper, (name, format)| {
+            helper
+                .try_with_format(name, format)
+                .map_err(|e| ConfigError(format!("Invalid format `{}`: {}", name, e)))
+        })?;
+
+        Ok(helper.with_strict(self.strict))
+    }
+}
+
+#[cfg(feature = "locale")]
+fn validate_locale(locale: &str) -> Result<&str, ConfigError> {
+    locale
+        .parse::<chrono::Locale>()
+        .map(|_locale| locale)
+        .map_err(|_e| ConfigError(format!("Invalid locale provided: {}", locale)))
+}
+
+#[cfg(not(feature = "locale"))]
+fn validate_locale(locale: &str) -> Result<&str, ConfigError> {
+    Err(ConfigError(format!(
+        "You need to enable the `locale` feature of `handlebars-chrono` for the `locale`={} setting to work.",
+        locale
+    )))
+}
+
+/// Error produced when building a helper from an invalid [`HelperConfig`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError(String);
+
+impl ConfigError {
+    fn invalid<E: Display>(what: &'static str) -> impl FnOnce(E) -> Self {
+        move |e| Self(format!("Invalid {}: {}", what, e))
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_works() {
+        let config: HelperConfig = toml::from_str::<BTreeMap<String, HelperConfig>>(
+            r#"
+            [datetime]
+            timezone = "-03:00"
+            weekend = ["fri", "sat"]
+            first_weekday = "sunday"
+            strict = false
+
+            [datetime.formats]
+            short = "%d/%m/%Y"
+            "#,
+        )
+        .expect("Invalid TOML")
+        .remove("datetime")
+        .expect("Missing section");
+
+        assert_eq!(config.timezone.as_deref(), Some("-03:00"));
+        assert_eq!(config.formats.get("short").map(String::as_str), Some("%d/%m/%Y"));
+        assert!(!config.strict);
+        assert_eq!(config.fallback, None);
+
+        let mut h = handlebars::Handlebars::new();
+        crate::HelperFamily::new(config.build().expect("Invalid config")).register(&mut h);
+
+        assert_eq!(
+            h.render_template(r#"{{datetime from_timestamp="618658211" format="short"}}"#, &json!({}))
+                .expect("Render error"),
+            "09/08/1989",
+            "Failed to render configured format and timezone"
+        );
+        assert_eq!(
+            h.render_template(
+                r#"{{#calendar_month 618658211}}{{weekdays.[0].short}}{{/calendar_month}}"#,
+                &json!({})
+            )
+            .expect("Render error"),
+            "Sun",
+            "Failed to use configured first weekday"
+        );
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" add_business_days="2" output_format="%F %a"}}"#,
+                &json!({})
+            )
+            .expect("Render error"),
+            "1989-08-13 Sun",
+            "Failed to use configured weekend"
+        );
+        assert_eq!(
+            h.render_template(r#"{{datetime from_timestamp="618658211" format="long"}}"#, &json!({}))
+                .expect("Render error"),
+            "",
+            "Failed to render empty fallback"
+        );
+
+        assert_eq!(HelperConfig::default(), toml::from_str("").expect("Invalid TOML"));
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!(toml::from_str::<HelperConfig>(r#"time_zone = "UTC""#).is_err());
+
+        let config = |value| serde_json::from_value::<HelperConfig>(value).expect("Invalid config");
+
+        assert!(config(json!({"timezone": "Mars/Olympus"})).build().is_err());
+        assert!(config(json!({"format_syntax": "cobol"})).build().is_err());
+        assert!(config(json!({"weekend": ["sat", "someday"]})).build().is_err());
+        assert!(config(json!({"first_weekday": "someday"})).build().is_err());
+        assert!(config(json!({"formats": {"broken": "%Q"}})).build().is_err());
+        assert!(config(json!({"locale": "xx_XX"})).build().is_err());
+
+        let mut h = handlebars::Handlebars::new();
+        h.register_helper("datetime", Box::new(HelperConfig::default().build().expect("Invalid config")));
+
+        assert!(
+            matches!(h.render_template(r#"{{datetime from_timestamp="yesterday"}}"#, &json!({})), Err(_e),),
+            "Failed to produce error in strict mode"
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::spec::Timezone;
+
+/// Upper bound of days searched for an occurrence, the 28 years after which the calendar repeats
+const MAX_DAYS: u32 = 28 * 366;
+
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// Which occurrence of a [`Cron`] expression the `from_cron` initializer picks, relative to its base
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CronOccurrence {
+    /// First occurrence strictly after the base, the default
+    #[default]
+    Next,
+    /// Last occurrence strictly before the base
+    Previous,
+}
+
+impl FromStr for CronOccurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "next" => Ok(Self::Next),
+            "previous" => Ok(Self::Previous),
+            _ => Err(format!("Unknown occurrence: {}. Supported values are next and previous", s)),
+        }
+    }
+}
+
+/// Cron expression, as in `0 9 * * MON-FRI`
+///
+/// Supports the 5 field form (minute, hour, day of month, month, day of week), the 6 field form with leading seconds,
+/// lists, ranges, steps, month and weekday names, and the `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`,
+/// `@midnight` and `@hourly` macros. As with cron, when both the day of month and the day of week are restricted,
+/// a day matching either one matches.
+///
+/// ```rust
+/// use chrono::DateTime;
+/// use handlebars_chrono::{Cron, Timezone};
+///
+/// let cron: Cron = "0 9 * * MON-FRI".parse().unwrap();
+/// let friday = DateTime::parse_from_rfc3339("2024-01-05T10:00:00+02:00").unwrap();
+/// let timezone = Timezone::Fixed(*friday.offset());
+///
+/// assert_eq!(cron.after(timezone, friday).unwrap().to_rfc3339(), "2024-01-08T09:00:00+02:00");
+/// assert_eq!(cron.before(timezone, friday).unwrap().to_rfc3339(), "2024-01-05T09:00:00+02:00");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cron {
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of month is `*` or `?`
+    any_day: bool,
+    /// Whether the day of week is `*` or `?`
+    any_weekday: bool,
+}
+
+impl FromStr for Cron {
+    type Err = CronError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = match s.trim().to_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            macro_name if macro_name.starts_with('@') => return Err(CronError(format!("Unsupported cron macro: {}", s.trim()))),
+            _ => s,
+        };
+
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let (seconds, fields) = match fields.as_slice() {
+            [seconds, rest @ ..] if rest.len() == 5 => (*seconds, rest),
+            fields if fields.len() == 5 => ("0", fields),
+            _ => {
+                return Err(CronError(format!(
+                    "Invalid cron expression: {}. Expected 5 or 6 fields, or a macro",
+                    s
+                )))
+            }
+        };
+
+        let (days, any_day) = field("day of month", fields[2], 1, 31, &[])?;
+        let (weekdays, any_weekday) = field("day of week", fields[4], 0, 7, &WEEKDAYS)?;
+
+        Ok(Self {
+            seconds: field("second", seconds, 0, 59, &[])?.0,
+            minutes: field("minute", fields[0], 0, 59, &[])?.0,
+            hours: field("hour", fields[1], 0, 23, &[])?.0,
+            days,
+            months: field("month", fields[3], 1, 12, &MONTHS)?.0,
+            // 7 is Sunday too
+            weekdays: (weekdays | weekdays >> 7) & 0x7f,
+            any_day,
+            any_weekday,
+        })
+    }
+}
+
+impl Cron {
+    /// First occurrence strictly after `after`, in the local time of `timezone`
+    ///
+    /// Local times skipped by a DST gap move past the gap, as with recurrence rules.
+    pub fn after(&self, timezone: Timezone, after: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let mut date = timezone.convert(after.to_utc()).date_naive();
+
+        for _ in 0..MAX_DAYS {
+            if self.matches(date) {
+                let occurrence = self
+                    .times()
+                    .into_iter()
+                    .filter_map(|time| timezone.localize(date.and_time(time)))
+                    .find(|occurrence| *occurrence > after);
+
+                if occurrence.is_some() {
+                    return occurrence;
+                }
+            }
+
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    /// Last occurrence strictly before `before`, in the local time of `timezone`
+    pub fn before(&self, timezone: Timezone, before: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let mut date = timezone.convert(before.to_utc()).date_naive();
+
+        for _ in 0..MAX_DAYS {
+            if self.matches(date) {
+                let occurrence = self
+                    .times()
+                    .into_iter()
+                    .rev()
+                    .filter_map(|time| timezone.localize(date.and_time(time)))
+                    .find(|occurrence| *occurrence < before);
+
+                if occurrence.is_some() {
+                    return occurrence;
+                }
+            }
+
+            date = date.pred_opt()?;
+        }
+
+        None
+    }
+
+    /// Whether a date matches the day of month, month and day of week fields
+    fn matches(&self, date: NaiveDate) -> bool {
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+
+        bit(self.months, date.month())
+            && if self.any_day || self.any_weekday {
+                day && weekday
+            } else {
+                day || weekday
+            }
+    }
+
+    /// Times of the day matching the hour, minute and second fields, in order
+    fn times(&self) -> Vec<NaiveTime> {
+        let values = |set: u64, max: u32| (0..=max).filter(move |value| bit(set, *value));
+
+        values(self.hours, 23)
+            .flat_map(|hour| values(self.minutes, 59).map(move |minute| (hour, minute)))
+            .flat_map(|(hour, minute)| values(self.seconds, 59).filter_map(move |second| NaiveTime::from_hms_opt(hour, minute, second)))
+            .collect()
+    }
+}
+
+/// Error produced by an invalid [`Cron`] expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronError(String);
+
+impl Display for CronError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CronError {}
+
+/// Whether a value is in a set of bits
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parses a field into a set of bits, and whether it is `*` or `?`
+///
+/// Each comma separated item is `*`, a value, a range as in `1-5` or a name range as in `MON-FRI`, optionally
+/// followed by a step, as in `*/15` or `10-40/10`. A value with a step runs up to `max`, as in `5/15`.
+fn field(name: &str, value: &str, min: u32, max: u32, names: &[&str]) -> Result<(u64, bool), CronError> {
+    let invalid = || CronError(format!("Invalid {} field: {}", name, value));
+
+    let number = |input: &str| -> Result<u32, CronError> {
+        let number = match names.iter().position(|name| name.eq_ignore_ascii_case(input)) {
+            Some(index) => index as u32 + min,
+            None => input.parse().map_err(|_e| invalid())?,
+        };
+
+        if (min..=max).contains(&number) {
+            Ok(number)
+        } else {
+            Err(invalid())
+        }
+    };
+
+    let mut set = 0u64;
+    for item in value.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?)),
+            None => (item, None),
+        };
+
+        let (start, end) = match (range, range.split_once('-')) {
+            ("*", _) | ("?", _) if name.starts_with("day") || range == "*" => (min, max),
+            (_, Some((start, end))) => (number(start)?, number(end)?),
+            (_, None) if step.is_some() => (number(range)?, max),
+            (_, None) => number(range).map(|value| (value, value))?,
+        };
+
+        if start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok((set, value.starts_with('*') || value.starts_with('?')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Offset;
+
+    #[test]
+    fn it_works() {
+        let date = |datetime| DateTime::parse_from_rfc3339(datetime).expect("Parse error");
+        let utc = Timezone::Fixed(chrono::Utc.fix());
+
+        for (expression, base, next, previous) in [
+            (
+                "0 9 * * MON-FRI",
+                "2024-01-05T10:00:00Z",
+                "2024-01-08T09:00:00+00:00",
+                "2024-01-05T09:00:00+00:00",
+            ),
+            (
+                "@daily",
+                "2024-01-01T00:00:00Z",
+                "2024-01-02T00:00:00+00:00",
+                "2023-12-31T00:00:00+00:00",
+            ),
+            (
+                "@hourly",
+                "2024-01-01T10:30:00Z",
+                "2024-01-01T11:00:00+00:00",
+                "2024-01-01T10:00:00+00:00",
+            ),
+            (
+                "@weekly",
+                "2024-01-01T00:00:00Z",
+                "2024-01-07T00:00:00+00:00",
+                "2023-12-31T00:00:00+00:00",
+            ),
+            (
+                "@yearly",
+                "2024-06-01T00:00:00Z",
+                "2025-01-01T00:00:00+00:00",
+                "2024-01-01T00:00:00+00:00",
+            ),
+            (
+                "*/15 * * * *",
+                "2024-01-01T10:07:30Z",
+                "2024-01-01T10:15:00+00:00",
+                "2024-01-01T10:00:00+00:00",
+            ),
+            (
+                "30 0 12 * * *",
+                "2024-01-01T12:00:29Z",
+                "2024-01-01T12:00:30+00:00",
+                "2023-12-31T12:00:30+00:00",
+            ),
+            (
+                "0 0 29 2 *",
+                "2024-03-01T00:00:00Z",
+                "2028-02-29T00:00:00+00:00",
+                "2024-02-29T00:00:00+00:00",
+            ),
+            (
+                "0 0 13 * FRI",
+                "2024-01-01T00:00:00Z",
+                "2024-01-05T00:00:00+00:00",
+                "2023-12-29T00:00:00+00:00",
+            ),
+            (
+                "0 0 13 * *",
+                "2024-01-01T00:00:00Z",
+                "2024-01-13T00:00:00+00:00",
+                "2023-12-13T00:00:00+00:00",
+            ),
+            (
+                "0 0 * * 7",
+                "2024-01-01T00:00:00Z",
+                "2024-01-07T00:00:00+00:00",
+                "2023-12-31T00:00:00+00:00",
+            ),
+            (
+                "0 12 1 jan,JUL ?",
+                "2024-03-01T00:00:00Z",
+                "2024-07-01T12:00:00+00:00",
+                "2024-01-01T12:00:00+00:00",
+            ),
+            (
+                "0 8-18/5 * * *",
+                "2024-01-01T13:00:00Z",
+                "2024-01-01T18:00:00+00:00",
+                "2024-01-01T08:00:00+00:00",
+            ),
+            (
+                "5/20 * * * *",
+                "2024-01-01T10:30:00Z",
+                "2024-01-01T10:45:00+00:00",
+                "2024-01-01T10:25:00+00:00",
+            ),
+        ] {
+            let cron = expression.parse::<Cron>().expect("Cron error");
+
+            assert_eq!(
+                cron.after(utc, date(base)).map(|datetime| datetime.to_rfc3339()).as_deref(),
+                Some(next),
+                "Failed to find next occurrence of {}",
+                expression
+            );
+            assert_eq!(
+                cron.before(utc, date(base)).map(|datetime| datetime.to_rfc3339()).as_deref(),
+                Some(previous),
+                "Failed to find previous occurrence of {}",
+                expression
+            );
+        }
+
+        assert_eq!(
+            "0 9 * * *".parse::<Cron>().ok().and_then(|cron| cron.after(
+                Timezone::Fixed(*date("2024-01-01T00:00:00+02:00").offset()),
+                date("2024-01-01T10:00:00Z")
+            )),
+            Some(date("2024-01-02T09:00:00+02:00"))
+        );
+
+        #[cfg(feature = "timezone")]
+        {
+            let sofia = "Europe/Sofia".parse::<Timezone>().expect("Timezone error");
+            let cron = "30 3 * * *".parse::<Cron>().expect("Cron error");
+
+            assert_eq!(
+                cron.after(sofia, date("2024-03-30T12:00:00+02:00"))
+                    .map(|datetime| datetime.to_rfc3339()),
+                Some("2024-03-31T04:30:00+03:00".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn it_craps() {
+        for expression in [
+            "",
+            "* * * *",
+            "* * * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "* * * FOO *",
+            "? * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "0 9 * * MON#2",
+            "0 9 L * *",
+            "@reboot",
+        ] {
+            assert!(
+                matches!(expression.parse::<Cron>(), Err(_e),),
+                "Failed to produce error with {}",
+                expression
+            );
+        }
+
+        let never = "0 0 30 2 *".parse::<Cron>().expect("Cron error");
+        let base = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").expect("Parse error");
+        assert_eq!(never.after(Timezone::Fixed(*base.offset()), base), None);
+        assert_eq!(never.before(Timezone::Fixed(*base.offset()), base), None);
+    }
+}
@@ -1,17 +1,29 @@
-#[cfg(feature = "locale")]
-use chrono::Locale;
-use chrono::{DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDateTime, TimeDelta, Timelike, Utc};
-#[cfg(feature = "timezone")]
-use chrono_tz::Tz;
-use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderError, RenderErrorReason};
-use std::num::ParseIntError;
-use std::str::FromStr;
-
-#[derive(Clone, Copy)]
+use chrono::format::{parse, Parsed};
+use chrono::{DateTime, Datelike, FixedOffset, Offset, TimeDelta, Timelike, Utc, Weekday};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, JsonValue, Output, RenderContext, RenderError, RenderErrorReason,
+    ScopedJson,
+};
+
+use crate::age;
+use crate::business::{BusinessDays, HolidayCalendar};
+use crate::calendar::{self, MonthOverflow};
+use crate::cron::CronOccurrence;
+use crate::format::{FormatCache, FormatError, FormatSyntax};
+use crate::presets::FormatPreset;
+use crate::spec::{DateTimeSpec, Finalizer, Initializer, Shift, Timezone};
+use crate::styles;
+use crate::weekday;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Clone)]
 /// Chrono DateTime helper for Handlebars
 ///
 /// # Registration
 ///
+/// `HandlebarsChronoDateTime::default()` is the zero-config helper, it replaces the unit struct of versions `0.2.*`.
+///
 /// ```rust
 /// use chrono::Utc;
 /// use handlebars::Handlebars;
@@ -19,11 +31,35 @@ use std::str::FromStr;
 /// use serde_json::json;
 ///
 /// let mut h = Handlebars::new();
-/// h.register_helper("datetime", Box::new(HandlebarsChronoDateTime));
+/// h.register_helper("datetime", Box::new(HandlebarsChronoDateTime::default()));
 ///
 /// assert_eq!(h.render_template(r#"{{datetime}}"#, &json!({})).map(|s| s.as_str()[..16].to_string()).expect("Render error"), Utc::now().to_rfc3339().as_str()[..16].to_string());
 /// ```
 ///
+/// # Business days
+///
+/// The business-day modifiers skip the weekend days, Saturday and Sunday by default, and the holidays of the
+/// registered holiday calendar, if any.
+///
+/// ```rust
+/// use chrono::Weekday;
+/// use handlebars::Handlebars;
+/// use handlebars_chrono::{HandlebarsChronoDateTime, MemoryHolidayCalendar};
+/// use serde_json::json;
+///
+/// let mut h = Handlebars::new();
+/// h.register_helper(
+///     "datetime",
+///     Box::new(
+///         HandlebarsChronoDateTime::default()
+///             .with_weekend(&[Weekday::Sat, Weekday::Sun])
+///             .with_holiday_calendar(MemoryHolidayCalendar::new().with_annual(12, 24).with_annual(12, 25)),
+///     ),
+/// );
+///
+/// assert_eq!(h.render_template(r#"{{datetime from_rfc3339="2024-12-20T10:00:00Z" add_business_days="2" output_format="%F"}}"#, &json!({})).expect("Render error"), "2024-12-26");
+/// ```
+///
 /// # Behavior
 ///
 /// TODO
@@ -36,17 +72,138 @@ use std::str::FromStr;
 ///
 ///
 ///
-pub struct HandlebarsChronoDateTime;
+pub struct HandlebarsChronoDateTime {
+    weekend: Vec<Weekday>,
+    holidays: Option<Arc<dyn HolidayCalendar>>,
+    presets: HashMap<String, FormatPreset>,
+    format_syntax: FormatSyntax,
+    formats: Arc<FormatCache>,
+    timezone: Option<Timezone>,
+    locale: Option<String>,
+    first_weekday: Weekday,
+    strict: bool,
+    fallback: String,
+}
 
-impl HelperDef for HandlebarsChronoDateTime {
-    fn call<'reg: 'rc, 'rc>(
-        &self,
-        h: &Helper<'rc>,
-        _r: &'reg Handlebars,
-        _ctx: &'rc Context,
-        _rc: &mut RenderContext<'reg, 'rc>,
-        out: &mut dyn Output,
-    ) -> HelperResult {
+impl Default for HandlebarsChronoDateTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HandlebarsChronoDateTime {
+    /// New helper with Saturday and Sunday as weekend days, Monday as the first day of the week and no holidays
+    pub fn new() -> Self {
+        Self {
+            weekend: vec![Weekday::Sat, Weekday::Sun],
+            holidays: None,
+            presets: HashMap::new(),
+            format_syntax: FormatSyntax::Strftime,
+            formats: Arc::new(FormatCache::default()),
+            timezone: None,
+            locale: None,
+            first_weekday: Weekday::Mon,
+            strict: true,
+            fallback: String::new(),
+        }
+    }
+
+    /// Sets the weekend days skipped by the business-day modifiers and finalizers
+    pub fn with_weekend(mut self, weekend: &[Weekday]) -> Self {
+        self.weekend = weekend.to_vec();
+        self
+    }
+
+    /// Sets the holiday calendar used by the business-day modifiers and finalizers
+    pub fn with_holiday_calendar<C: HolidayCalendar + 'static>(mut self, calendar: C) -> Self {
+        self.holidays = Some(Arc::new(calendar));
+        self
+    }
+
+    /// Registers a named strftime output format, selected with the `format` hash parameter
+    ///
+    /// # Panics
+    ///
+    /// If the format is invalid, see [`HandlebarsChronoDateTime::try_with_format`] to handle the error instead.
+    pub fn with_format<N: Into<String>, F: Into<String>>(self, name: N, format: F) -> Self {
+        self.with_format_preset(name, FormatPreset::new(format))
+    }
+
+    /// Same as [`HandlebarsChronoDateTime::with_format`], returning an error on invalid formats instead of panicking
+    pub fn try_with_format<N: Into<String>, F: Into<String>>(self, name: N, format: F) -> Result<Self, FormatError> {
+        self.try_with_format_preset(name, FormatPreset::new(format))
+    }
+
+    /// Registers a named output format preset, selected with the `format` hash parameter
+    ///
+    /// Registered presets take precedence over the built-in ones with the same name.
+    /// The format is compiled right away.
+    ///
+    /// # Panics
+    ///
+    /// If the format is invalid, see [`HandlebarsChronoDateTime::try_with_format_preset`] to handle the error instead.
+    pub fn with_format_preset<N: Into<String>>(self, name: N, preset: FormatPreset) -> Self {
+        let name = name.into();
+
+        match self.try_with_format_preset(name.as_str(), preset) {
+            Ok(helper) => helper,
+            Err(e) => panic!("Invalid format preset `{}`: {}", name, e),
+        }
+    }
+
+    /// Same as [`HandlebarsChronoDateTime::with_format_preset`], returning an error on invalid formats instead of panicking
+    pub fn try_with_format_preset<N: Into<String>>(mut self, name: N, preset: FormatPreset) -> Result<Self, FormatError> {
+        self.formats.validate(preset.format())?;
+        self.presets.insert(name.into(), preset);
+
+        Ok(self)
+    }
+
+    /// Sets the default syntax of the `output_format` hash parameter, which can still be overridden with `format_syntax`
+    pub fn with_format_syntax(mut self, format_syntax: FormatSyntax) -> Self {
+        self.format_syntax = format_syntax;
+        self
+    }
+
+    /// Sets the default timezone, used when the `with_timezone` hash parameter is not given
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+
+    /// Sets the default locale, used when the `locale` hash parameter is not given
+    pub fn with_locale<L: Into<String>>(mut self, locale: L) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Sets the first day of the week of `calendar_month` and of weekly groups, Monday by default
+    pub fn with_first_weekday(mut self, first_weekday: Weekday) -> Self {
+        self.first_weekday = first_weekday;
+        self
+    }
+
+    /// Sets whether invalid parameters and dates fail the render (the default), or render the fallback text instead
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets the text rendered in place of errors when not strict, empty by default
+    pub fn with_fallback<F: Into<String>>(mut self, fallback: F) -> Self {
+        self.fallback = fallback.into();
+        self
+    }
+
+    /// Looks up a registered or built-in output format preset
+    fn preset(&self, name: &str) -> Option<FormatPreset> {
+        self.presets.get(name).cloned().or_else(|| FormatPreset::builtin(name))
+    }
+}
+
+impl HandlebarsChronoDateTime {
+    /// Runs the initializer and the modifiers of a spec
+    pub(crate) fn evaluate(&self, spec: &DateTimeSpec) -> Result<DateTime<FixedOffset>, String> {
         // INITIALIZERS
         //
         // default Utc::now()
@@ -57,83 +214,49 @@ impl HelperDef for HandlebarsChronoDateTime {
         // parse_from_rfc2822
         // parse_from_rfc3339
         // parse_from_str + input_format
-        let datetime = if let Some(timestamp) = h.hash_get("from_timestamp") {
-            let timestamp = timestamp.render();
-
-            DateTime::from_timestamp(
-                timestamp.parse().map_err(|e: ParseIntError| {
-                    <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid seconds timestamp: {}", e)))
-                })?,
-                0,
-            )
-            .ok_or::<RenderError>(RenderErrorReason::Other("Out-of-range number of seconds".to_string()).into())?
-        } else if let Some(timestamp) = h.hash_get("from_timestamp_millis") {
-            let timestamp = timestamp.render();
-
-            DateTime::from_timestamp_millis(timestamp.parse().map_err(|e: ParseIntError| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid milli-seconds timestamp: {}", e)))
-            })?)
-            .ok_or::<RenderError>(RenderErrorReason::Other("Out-of-range number of milliseconds".to_string()).into())?
-        } else if let Some(timestamp) = h.hash_get("from_timestamp_micros") {
-            let timestamp = timestamp.render();
-
-            DateTime::from_timestamp_micros(timestamp.parse().map_err(|e: ParseIntError| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid micro-seconds timestamp: {}", e)))
-            })?)
-            .ok_or::<RenderError>(
-                RenderErrorReason::Other(
-                    "Number of microseconds would be out of range for a NaiveDateTime (more than ca. 262,000 years away from common era)"
-                        .to_string(),
-                )
-                .into(),
-            )?
-        } else if let Some(timestamp) = h.hash_get("from_timestamp_nanos") {
-            let timestamp = timestamp.render();
-
-            DateTime::from_timestamp_nanos(timestamp.parse().map_err(|e: ParseIntError| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid nano-seconds timestamp: {}", e)))
-            })?)
-        } else if let Some(input_str) = h.hash_get("from_rfc2822") {
-            let input_str = input_str.render();
-
-            DateTime::parse_from_rfc2822(&input_str)
-                .map_err(|e| {
-                    <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!(
-                        "Invalid RFC2822 datetime format: {}",
-                        e
-                    )))
-                })?
-                .to_utc()
-        } else if let Some(input_str) = h.hash_get("from_rfc3339") {
-            let input_str = input_str.render();
-
-            DateTime::parse_from_rfc3339(&input_str)
-                .map_err(|e| {
-                    <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!(
-                        "Invalid RFC3339 datetime format: {}",
-                        e
-                    )))
-                })?
-                .to_utc()
-        } else if let Some(input_str) = h.hash_get("from_str") {
-            if let Some(input_format) = h.hash_get("input_format") {
-                let input_str = input_str.render();
-                let input_format = input_format.render();
-
-                NaiveDateTime::parse_from_str(&input_str, &input_format)
-                    .map_err(|e| {
-                        <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!(
-                            "Invalid datetime format or format doesn't match input: {}",
-                            e
-                        )))
-                    })?
+        // from_rrule + dtstart (+ after)
+        // from_cron (+ occurrence + after)
+        let datetime = match &spec.initializer {
+            Initializer::Now => Utc::now(),
+            Initializer::Timestamp(timestamp) => {
+                DateTime::from_timestamp(*timestamp, 0).ok_or_else(|| "Out-of-range number of seconds".to_string())?
+            }
+            Initializer::TimestampMillis(timestamp) => {
+                DateTime::from_timestamp_millis(*timestamp).ok_or_else(|| "Out-of-range number of milliseconds".to_string())?
+            }
+            Initializer::TimestampMicros(timestamp) => DateTime::from_timestamp_micros(*timestamp).ok_or_else(|| {
+                "Number of microseconds would be out of range for a NaiveDateTime (more than ca. 262,000 years away from common era)"
+                    .to_string()
+            })?,
+            Initializer::TimestampNanos(timestamp) => DateTime::from_timestamp_nanos(*timestamp),
+            Initializer::Rfc2822(datetime) | Initializer::Rfc3339(datetime) => datetime.to_utc(),
+            Initializer::Str { input, format } => {
+                let input_format = self.formats.input(format).map_err(|e| format!("Invalid input format: {}", e))?;
+
+                let mut parsed = Parsed::new();
+                parse(&mut parsed, input, input_format.iter())
+                    .and_then(|_| parsed.to_naive_datetime_with_offset(0))
+                    .map_err(|e| format!("Invalid datetime format or format doesn't match input: {}", e))?
                     .and_utc()
-            } else {
-                // error, missing input format
-                return Err(RenderErrorReason::Other("Missing `input_format` hash parameter".to_string()).into());
             }
-        } else {
-            Utc::now()
+            Initializer::RRule { rule, dtstart, after } => {
+                let after = after.unwrap_or_else(|| Utc::now().fixed_offset());
+
+                rule.after(*dtstart, self.recurrence_timezone(spec), after)
+                    .ok_or_else(|| format!("No occurrence of the recurrence rule after {}", after.to_rfc3339()))?
+                    .to_utc()
+            }
+            Initializer::Cron { cron, occurrence, after } => {
+                let base = after.unwrap_or_else(|| Utc::now().fixed_offset());
+                let timezone = self.recurrence_timezone(spec);
+
+                match occurrence {
+                    CronOccurrence::Next => cron.after(timezone, base),
+                    CronOccurrence::Previous => cron.before(timezone, base),
+                }
+                .ok_or_else(|| format!("No occurrence of the cron expression around {}", base.to_rfc3339()))?
+                .to_utc()
+            }
         };
 
         // MODIFIERS (by default everything is converted to UTC by the initializer)
@@ -150,555 +273,559 @@ impl HelperDef for HandlebarsChronoDateTime {
         // with_minute
         // with_second
         // with_nanosecond
-        // add_months
+        // next_weekday
+        // previous_weekday
+        // nth_weekday_of_month
+        // add_years (+ month_overflow)
+        // add_quarters (+ month_overflow)
+        // add_months (+ month_overflow)
+        // sub_years, sub_quarters, sub_months (+ month_overflow)
+        // add, sub (years and months of the ISO 8601 durations + month_overflow)
         // add_weeks
         // add_days
+        // add_business_days (+ weekend)
         // add_hours
         // add_minutes
         // add_seconds
         // add_milliseconds
         // add_microseconds
         // add_nanoseconds
-        // sub_months
-        // sub_weeks
-        // sub_days
-        // sub_hours
-        // sub_minutes
-        // sub_seconds
-        // sub_milliseconds
-        // sub_microseconds
-        // sub_nanoseconds
-        let datetime = if let Some(timezone) = h.hash_get("with_timezone") {
-            let timezone = timezone.render();
-            let tz: FixedOffset = if timezone.to_lowercase() == "local" {
-                Local::now().fixed_offset().timezone()
-            } else if timezone.contains('0') {
-                if let Ok(tz) = FixedOffset::from_str(&timezone) {
-                    tz
-                } else {
-                    return Err(RenderErrorReason::Other(
-                        "Failed to parse timezone offset. Supported values are IANA timezones, local or valid fixed offset".to_string(),
-                    )
-                    .into());
-                }
-            } else {
-                #[cfg(feature = "timezone")]
-                if let Ok(tz) = timezone.parse::<Tz>() {
-                    datetime.with_timezone(&tz).fixed_offset().timezone()
-                } else {
-                    return Err(RenderErrorReason::Other(
-                        "Failed to parse IANA timezone. Supported values are IANA timezones, local or valid fixed offset".to_string(),
-                    )
-                    .into());
-                }
-
-                #[cfg(not(feature = "timezone"))]
-                return Err(RenderErrorReason::Other(
-                    "You need to enable the `timezone` feature of the `handlebars-chrono` create for IANA timezones to work.".to_string(),
-                )
-                .into());
-            };
-
-            datetime.with_timezone(&tz)
-        } else {
-            datetime.fixed_offset()
+        // sub_* (same as add_*)
+        // add (rest of the ISO 8601 duration)
+        // sub (rest of the ISO 8601 duration)
+        let modifiers = &spec.modifiers;
+        let business_days = self.business_days(spec);
+
+        let datetime = match modifiers.timezone.or(self.timezone) {
+            Some(timezone) => timezone.convert(datetime),
+            None => datetime.fixed_offset(),
         };
 
-        let datetime = if let Some(day) = h.hash_get("with_ordinal") {
-            let day = day.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid ordinal parameter: {}", e)))
-            })?;
-
+        let datetime = if let Some(day) = modifiers.ordinal {
             datetime
                 .with_ordinal(day)
-                .ok_or::<RenderError>(RenderErrorReason::Other("Ordinal parameter out of range".to_string()).into())?
+                .ok_or_else(|| "Ordinal parameter out of range".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(day) = h.hash_get("with_ordinal0") {
-            let day = day.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid ordinal parameter: {}", e)))
-            })?;
-
+        let datetime = if let Some(day) = modifiers.ordinal0 {
             datetime
                 .with_ordinal0(day)
-                .ok_or::<RenderError>(RenderErrorReason::Other("Ordinal parameter out of range".to_string()).into())?
+                .ok_or_else(|| "Ordinal parameter out of range".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(year) = h.hash_get("with_year") {
-            let year = year.render().parse::<i32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid year parameter: {}", e)))
-            })?;
-
+        let datetime = if let Some(year) = modifiers.year {
             datetime
                 .with_year(year)
-                .ok_or::<RenderError>(RenderErrorReason::Other("Year parameter out of range or produces invalid date".to_string()).into())?
+                .ok_or_else(|| "Year parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(month) = h.hash_get("with_month") {
-            let month = month.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid month parameter: {}", e)))
-            })?;
-
-            datetime.with_month(month).ok_or::<RenderError>(
-                RenderErrorReason::Other("Month parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(month) = modifiers.month {
+            datetime
+                .with_month(month)
+                .ok_or_else(|| "Month parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(month) = h.hash_get("with_month0") {
-            let month = month.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid month parameter: {}", e)))
-            })?;
-
-            datetime.with_month0(month).ok_or::<RenderError>(
-                RenderErrorReason::Other("Month parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(month) = modifiers.month0 {
+            datetime
+                .with_month0(month)
+                .ok_or_else(|| "Month parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(day) = h.hash_get("with_day") {
-            let day = day.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid day parameter: {}", e)))
-            })?;
-
+        let datetime = if let Some(day) = modifiers.day {
             datetime
                 .with_day(day)
-                .ok_or::<RenderError>(RenderErrorReason::Other("Day parameter out of range or produces invalid date".to_string()).into())?
+                .ok_or_else(|| "Day parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(day) = h.hash_get("with_day0") {
-            let day = day.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid day parameter: {}", e)))
-            })?;
-
+        let datetime = if let Some(day) = modifiers.day0 {
             datetime
                 .with_day0(day)
-                .ok_or::<RenderError>(RenderErrorReason::Other("Day parameter out of range or produces invalid date".to_string()).into())?
+                .ok_or_else(|| "Day parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(hour) = h.hash_get("with_hour") {
-            let hour = hour.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid hour parameter: {}", e)))
-            })?;
-
+        let datetime = if let Some(hour) = modifiers.hour {
             datetime
                 .with_hour(hour)
-                .ok_or::<RenderError>(RenderErrorReason::Other("Hour parameter out of range or produces invalid date".to_string()).into())?
+                .ok_or_else(|| "Hour parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(min) = h.hash_get("with_minute") {
-            let min = min.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid minute parameter: {}", e)))
-            })?;
-
-            datetime.with_minute(min).ok_or::<RenderError>(
-                RenderErrorReason::Other("Minute parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(min) = modifiers.minute {
+            datetime
+                .with_minute(min)
+                .ok_or_else(|| "Minute parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(sec) = h.hash_get("with_second") {
-            let sec = sec.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid second parameter: {}", e)))
-            })?;
-
-            datetime.with_second(sec).ok_or::<RenderError>(
-                RenderErrorReason::Other("Second parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(sec) = modifiers.second {
+            datetime
+                .with_second(sec)
+                .ok_or_else(|| "Second parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(nsec) = h.hash_get("with_nanosecond") {
-            let nsec = nsec.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid nano-second parameter: {}", e)))
-            })?;
-
-            datetime.with_nanosecond(nsec).ok_or::<RenderError>(
-                RenderErrorReason::Other("Nano-second parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(nsec) = modifiers.nanosecond {
+            datetime
+                .with_nanosecond(nsec)
+                .ok_or_else(|| "Nano-second parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        // add_
+        // weekday navigation
 
-        let datetime = if let Some(months) = h.hash_get("add_months") {
-            let months = months.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid months parameter: {}", e)))
-            })?;
-
-            datetime.checked_add_months(Months::new(months)).ok_or::<RenderError>(
-                RenderErrorReason::Other("Months parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(day) = modifiers.next_weekday {
+            weekday::next(datetime.date_naive(), day)
+                .and_then(|date| calendar::add_days(datetime, (date - datetime.date_naive()).num_days()))
+                .ok_or_else(|| "Next weekday produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(weeks) = h.hash_get("add_weeks") {
-            let weeks = weeks.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid weeks parameter: {}", e)))
-            })?;
-
-            datetime
-                .checked_add_signed(
-                    TimeDelta::try_weeks(weeks)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Weeks parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Weeks parameter out of range or produces invalid date".to_string()).into(),
-                )?
+        let datetime = if let Some(day) = modifiers.previous_weekday {
+            weekday::previous(datetime.date_naive(), day)
+                .and_then(|date| calendar::add_days(datetime, (date - datetime.date_naive()).num_days()))
+                .ok_or_else(|| "Previous weekday produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(days) = h.hash_get("add_days") {
-            let days = days.render().parse::<u64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid days parameter: {}", e)))
-            })?;
-
-            datetime
-                .checked_add_days(Days::new(days))
-                .ok_or::<RenderError>(RenderErrorReason::Other("Days parameter out of range or produces invalid date".to_string()).into())?
+        let datetime = if let Some(nth) = modifiers.nth_weekday_of_month {
+            nth.in_month(datetime.year(), datetime.month())
+                .and_then(|date| datetime.with_day(date.day()))
+                .ok_or_else(|| "Nth weekday of month parameter doesn't exist in this month".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(hours) = h.hash_get("add_hours") {
-            let hours = hours.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid hours parameter: {}", e)))
-            })?;
+        // months first, so that the month overflow policy checks every month step against the day it started from
 
-            datetime
-                .checked_add_signed(
-                    TimeDelta::try_hours(hours)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Hours parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Hours parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+        let datetime = shift_months(datetime, &modifiers.add, 1, modifiers.month_overflow)?;
+        let datetime = shift_months(datetime, &modifiers.sub, -1, modifiers.month_overflow)?;
 
-        let datetime = if let Some(min) = h.hash_get("add_minutes") {
-            let min = min.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid minutes parameter: {}", e)))
-            })?;
+        let sub_duration = modifiers
+            .sub_duration
+            .map(|duration| duration.negated().ok_or_else(|| "Duration out of range".to_string()))
+            .transpose()?;
 
-            datetime
-                .checked_add_signed(
-                    TimeDelta::try_minutes(min)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Minutes parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Minutes parameter out of range or produces invalid date".to_string()).into(),
-                )?
+        let datetime = if let Some(duration) = modifiers.add_duration {
+            duration
+                .add_months_to(datetime, modifiers.month_overflow)
+                .ok_or_else(|| "Duration parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(sec) = h.hash_get("add_seconds") {
-            let sec = sec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid seconds parameter: {}", e)))
-            })?;
-
-            datetime
-                .checked_add_signed(
-                    TimeDelta::try_seconds(sec)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Seconds parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Seconds parameter out of range or produces invalid date".to_string()).into(),
-                )?
+        let datetime = if let Some(duration) = sub_duration {
+            duration
+                .add_months_to(datetime, modifiers.month_overflow)
+                .ok_or_else(|| "Duration parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(msec) = h.hash_get("add_milliseconds") {
-            let msec = msec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid milli-seconds parameter: {}", e)))
-            })?;
+        // add_ and sub_
 
-            datetime
-                .checked_add_signed(
-                    TimeDelta::try_milliseconds(msec)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Milli-seconds parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Milli-seconds parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+        let datetime = shift(datetime, &modifiers.add, 1, &business_days)?;
+        let datetime = shift(datetime, &modifiers.sub, -1, &business_days)?;
 
-        let datetime = if let Some(usec) = h.hash_get("add_microseconds") {
-            let usec = usec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid micro-seconds parameter: {}", e)))
-            })?;
+        // ISO 8601 durations
 
-            datetime.checked_add_signed(TimeDelta::microseconds(usec)).ok_or::<RenderError>(
-                RenderErrorReason::Other("Micro-seconds parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(duration) = modifiers.add_duration {
+            duration
+                .add_days_to(datetime)
+                .ok_or_else(|| "Duration parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        let datetime = if let Some(nsec) = h.hash_get("add_nanoseconds") {
-            let nsec = nsec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid nano-seconds parameter: {}", e)))
-            })?;
-
-            datetime.checked_add_signed(TimeDelta::nanoseconds(nsec)).ok_or::<RenderError>(
-                RenderErrorReason::Other("Nano-seconds parameter out of range or produces invalid date".to_string()).into(),
-            )?
+        let datetime = if let Some(duration) = sub_duration {
+            duration
+                .add_days_to(datetime)
+                .ok_or_else(|| "Duration parameter out of range or produces invalid date".to_string())?
         } else {
             datetime
         };
 
-        // sub_
+        Ok(datetime)
+    }
+
+    /// Runs the whole pipeline of a spec, finalizer included
+    pub(crate) fn render(&self, spec: &DateTimeSpec) -> Result<String, String> {
+        self.output(spec).map(|output| match output {
+            JsonValue::String(output) => output,
+            output => output.to_string(),
+        })
+    }
 
-        let datetime = if let Some(months) = h.hash_get("sub_months") {
-            let months = months.render().parse::<u32>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid months parameter: {}", e)))
-            })?;
+    /// Output of a spec as a JSON value, a string for every finalizer but the object of `age_output="json"`
+    pub(crate) fn output(&self, spec: &DateTimeSpec) -> Result<JsonValue, String> {
+        let datetime = self.evaluate(spec)?;
 
-            datetime.checked_sub_months(Months::new(months)).ok_or::<RenderError>(
-                RenderErrorReason::Other("Months parameter out of range or produces invalid date".to_string()).into(),
-            )?
-        } else {
-            datetime
+        // FINALIZERS
+        //
+        // format - output_format (+ format_syntax) or format (preset) or date_style/time_style
+        // format_localized - output_format or format (preset) or date_style/time_style + locale
+        // to_rfc3339 (default)
+        // to_rfc2822
+        // timestamp
+        // timestamp_millis
+        // timestamp_micros
+        // timestamp_nanos
+        // years_since + (parse_from_rfc3339)
+        // business_days_until + (parse_from_rfc3339)
+        // to_calendar (+ calendar_base and calendar_* formats) + locale
+        // age_since + (parse_from_rfc3339) (+ age_precision, age_output and age_separator) + locale
+        let locale = self.locale(spec.locale.as_deref());
+
+        let output_format = match &spec.finalizer {
+            Finalizer::OutputFormat { format, syntax } => {
+                let output_format = self
+                    .formats
+                    .output(format, syntax.unwrap_or(self.format_syntax), locale)
+                    .map_err(|e| format!("Invalid output format: {}", e))?;
+
+                Some((output_format, datetime))
+            }
+            Finalizer::Preset(name) => {
+                let preset = self.preset(name).ok_or_else(|| format!("Unknown format preset: {}", name))?;
+                let output_format = self
+                    .formats
+                    .output(preset.format(), FormatSyntax::Strftime, locale.filter(|_| preset.is_localized()))
+                    .map_err(|e| format!("Invalid format preset: {}", e))?;
+
+                Some((
+                    output_format,
+                    if preset.is_utc() {
+                        datetime.to_utc().fixed_offset()
+                    } else {
+                        datetime
+                    },
+                ))
+            }
+            Finalizer::Style { date, time } => {
+                let pattern = styles::pattern(locale.unwrap_or("en_US"), *date, *time);
+                let output_format = self
+                    .formats
+                    .output(&pattern, FormatSyntax::Strftime, locale)
+                    .map_err(|e| format!("Invalid style: {}", e))?;
+
+                Some((output_format, datetime))
+            }
+            Finalizer::Calendar { base, formats } => {
+                let base = base.unwrap_or_else(|| Utc::now().fixed_offset());
+                let output_format = self
+                    .formats
+                    .output(&formats.pattern(&datetime, &base, locale), FormatSyntax::Strftime, locale)
+                    .map_err(|e| format!("Invalid calendar format: {}", e))?;
+
+                Some((output_format, datetime))
+            }
+            _ => None,
         };
 
-        let datetime = if let Some(weeks) = h.hash_get("sub_weeks") {
-            let weeks = weeks.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid weeks parameter: {}", e)))
-            })?;
+        if let Some((output_format, datetime)) = output_format {
+            #[cfg(not(feature = "locale"))]
+            if let Some(locale) = locale {
+                return Err(format!(
+                    "You need to enable the `locale` feature of `handlebars-chrono` for the `locale`={} param to work.",
+                    locale
+                ));
+            }
+
+            return output_format.render(&datetime).map(JsonValue::String);
+        }
 
-            datetime
-                .checked_sub_signed(
-                    TimeDelta::try_weeks(weeks)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Weeks parameter out of range".to_string()).into())?,
+        let output = match &spec.finalizer {
+            Finalizer::Rfc2822 => datetime.to_rfc2822(),
+            Finalizer::Timestamp => datetime.timestamp().to_string(),
+            Finalizer::TimestampMillis => datetime.timestamp_millis().to_string(),
+            Finalizer::TimestampMicros => datetime.timestamp_micros().to_string(),
+            Finalizer::TimestampNanos => datetime
+                .timestamp_nanos_opt()
+                .ok_or_else(|| {
+                    "An i64 with nanosecond precision can span a range of ~584 years. This timestamp is out of range.".to_string()
+                })?
+                .to_string(),
+            Finalizer::YearsSince(base_datetime) => datetime
+                .years_since(base_datetime.to_utc().into())
+                .ok_or_else(|| "Negative range, try swapping the parameters.".to_string())?
+                .to_string(),
+            Finalizer::BusinessDaysUntil(until_datetime) => self
+                .business_days(spec)
+                .between(
+                    datetime.date_naive(),
+                    until_datetime.with_timezone(&datetime.timezone()).date_naive(),
                 )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Weeks parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+                .ok_or_else(|| {
+                    format!(
+                        "Business days range too wide, at most {} days can be counted",
+                        BusinessDays::MAX_SPAN
+                    )
+                })?
+                .to_string(),
+            Finalizer::Age {
+                since,
+                precision,
+                json,
+                separator,
+            } => {
+                let parts = age::breakdown(*since, datetime, *precision)
+                    .ok_or_else(|| "Negative range, try swapping the parameters.".to_string())?;
+
+                if *json {
+                    return Ok(age::json(&parts));
+                }
 
-        let datetime = if let Some(days) = h.hash_get("sub_days") {
-            let days = days.render().parse::<u64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid days parameter: {}", e)))
-            })?;
+                #[cfg(not(feature = "locale"))]
+                if let Some(locale) = locale {
+                    return Err(format!(
+                        "You need to enable the `locale` feature of `handlebars-chrono` for the `locale`={} param to work.",
+                        locale
+                    ));
+                }
 
-            datetime
-                .checked_sub_days(Days::new(days))
-                .ok_or::<RenderError>(RenderErrorReason::Other("Days parameter out of range or produces invalid date".to_string()).into())?
-        } else {
-            datetime
+                age::text(&parts, separator.as_deref(), locale)?
+            }
+            // DEFAULT to_rfc3339
+            _ => datetime.to_rfc3339(),
         };
 
-        let datetime = if let Some(hours) = h.hash_get("sub_hours") {
-            let hours = hours.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid hours parameter: {}", e)))
-            })?;
+        Ok(JsonValue::String(output))
+    }
 
-            datetime
-                .checked_sub_signed(
-                    TimeDelta::try_hours(hours)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Hours parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Hours parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+    /// Formats a date time with a strftime format in the given locale, through the cache of compiled formats
+    pub(crate) fn format(&self, datetime: &DateTime<FixedOffset>, format: &str, locale: Option<&str>) -> Result<String, String> {
+        #[cfg(not(feature = "locale"))]
+        if let Some(locale) = locale {
+            return Err(format!(
+                "You need to enable the `locale` feature of `handlebars-chrono` for the `locale`={} param to work.",
+                locale
+            ));
+        }
+
+        self.formats
+            .output(format, FormatSyntax::Strftime, locale)
+            .map_err(|e| format!("Invalid output format: {}", e))?
+            .render(datetime)
+    }
 
-        let datetime = if let Some(min) = h.hash_get("sub_minutes") {
-            let min = min.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid minutes parameter: {}", e)))
-            })?;
+    /// Timezone recurrence rules and cron expressions are evaluated in, UTC unless set on the spec or the helper
+    fn recurrence_timezone(&self, spec: &DateTimeSpec) -> Timezone {
+        spec.modifiers.timezone.or(self.timezone).unwrap_or(Timezone::Fixed(Utc.fix()))
+    }
 
-            datetime
-                .checked_sub_signed(
-                    TimeDelta::try_minutes(min)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Minutes parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Minutes parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+    /// Default timezone of the helper
+    pub(crate) fn timezone(&self) -> Option<Timezone> {
+        self.timezone
+    }
 
-        let datetime = if let Some(sec) = h.hash_get("sub_seconds") {
-            let sec = sec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid seconds parameter: {}", e)))
-            })?;
+    /// Weekend days of the helper
+    pub(crate) fn weekend(&self) -> &[Weekday] {
+        &self.weekend
+    }
 
-            datetime
-                .checked_sub_signed(
-                    TimeDelta::try_seconds(sec)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Seconds parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Seconds parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+    /// First day of the week of the helper
+    pub(crate) fn first_weekday(&self) -> Weekday {
+        self.first_weekday
+    }
 
-        let datetime = if let Some(msec) = h.hash_get("sub_milliseconds") {
-            let msec = msec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid milli-seconds parameter: {}", e)))
-            })?;
+    /// Locale of a call, falling back to the default locale of the helper
+    pub(crate) fn locale<'a>(&'a self, locale: Option<&'a str>) -> Option<&'a str> {
+        locale.or(self.locale.as_deref())
+    }
 
-            datetime
-                .checked_sub_signed(
-                    TimeDelta::try_milliseconds(msec)
-                        .ok_or::<RenderError>(RenderErrorReason::Other("Milli-seconds parameter out of range".to_string()).into())?,
-                )
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other("Milli-seconds parameter out of range or produces invalid date".to_string()).into(),
-                )?
-        } else {
-            datetime
-        };
+    /// Output of a helper, or the fallback text in place of errors when not strict
+    pub(crate) fn recover<T: Into<JsonValue>>(&self, result: Result<T, String>) -> Result<JsonValue, RenderError> {
+        self.recover_or(result, JsonValue::String(self.fallback.clone()))
+    }
 
-        let datetime = if let Some(usec) = h.hash_get("sub_microseconds") {
-            let usec = usec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid micro-seconds parameter: {}", e)))
-            })?;
+    /// Output of a helper, or the given value in place of errors when not strict
+    ///
+    /// For helpers used in conditions and loops, where the fallback text would be truthy or iterated over.
+    pub(crate) fn recover_or<T: Into<JsonValue>>(&self, result: Result<T, String>, fallback: JsonValue) -> Result<JsonValue, RenderError> {
+        match result {
+            Ok(output) => Ok(output.into()),
+            Err(_e) if !self.strict => Ok(fallback),
+            Err(e) => Err(<RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(e))),
+        }
+    }
 
-            datetime.checked_sub_signed(TimeDelta::microseconds(usec)).ok_or::<RenderError>(
-                RenderErrorReason::Other("Micro-seconds parameter out of range or produces invalid date".to_string()).into(),
-            )?
-        } else {
-            datetime
-        };
+    /// Business day rules of a spec, falling back to the weekend of the helper
+    fn business_days<'a>(&'a self, spec: &'a DateTimeSpec) -> BusinessDays<'a> {
+        BusinessDays {
+            weekend: spec.modifiers.weekend.as_deref().unwrap_or(&self.weekend),
+            holidays: self.holidays.as_deref(),
+        }
+    }
+}
 
-        let datetime = if let Some(nsec) = h.hash_get("sub_nanoseconds") {
-            let nsec = nsec.render().parse::<i64>().map_err(|e| {
-                <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!("Invalid nano-seconds parameter: {}", e)))
-            })?;
+impl HelperDef for HandlebarsChronoDateTime {
+    /// Value of the helper in subexpressions, as in `{{#with (datetime age_since=birth_date age_output="json")}}`
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let output = DateTimeSpec::parse(h.hash().iter().map(|(key, value)| (*key, value.render()))).and_then(|spec| self.output(&spec));
 
-            datetime.checked_sub_signed(TimeDelta::nanoseconds(nsec)).ok_or::<RenderError>(
-                RenderErrorReason::Other("Nano-seconds parameter out of range or produces invalid date".to_string()).into(),
-            )?
-        } else {
-            datetime
-        };
+        Ok(self.recover(output)?.into())
+    }
 
-        // FINALIZERS
+    /// Output of the helper written as is, without HTML escaping, with the object of `age_output="json"` as JSON text
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let output = DateTimeSpec::parse(h.hash().iter().map(|(key, value)| (*key, value.render()))).and_then(|spec| self.render(&spec));
 
-        // format - output_format
-        // format_localized - output_format + locale
-        // to_rfc3339 (default)
-        // to_rfc2822
-        // timestamp
-        // timestamp_millis
-        // timestamp_micros
-        // timestamp_nanos
-        // years_since + (parse_from_rfc3339)
-        let output = if let Some(output_format) = h.hash_get("output_format") {
-            let output_format = output_format.render();
-
-            if let Some(locale) = h.hash_get("locale") {
-                let locale = locale.render();
-                #[cfg(feature = "locale")]
-                {
-                    let locale = Locale::from_str(&locale).map_err(|_e| {
-                        <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!(
-                            "Invalid locale provided: {}",
-                            &locale
-                        )))
-                    })?;
-
-                    format!("{}", datetime.format_localized(&output_format, locale))
-                }
-                #[cfg(not(feature = "locale"))]
-                return Err(RenderErrorReason::Other(format!(
-                    "You need to enable the `locale` feature of `handlebars-chrono` for the `locale`={} param to work.",
-                    locale
-                ))
-                .into());
-            } else {
-                format!("{}", datetime.format(&output_format))
-            }
-        } else if h.hash_get("to_rfc2822").is_some() {
-            datetime.to_rfc2822()
-        } else if h.hash_get("to_timestamp").is_some() {
-            datetime.timestamp().to_string()
-        } else if h.hash_get("to_timestamp_millis").is_some() {
-            datetime.timestamp_millis().to_string()
-        } else if h.hash_get("to_timestamp_micros").is_some() {
-            datetime.timestamp_micros().to_string()
-        } else if h.hash_get("to_timestamp_nanos").is_some() {
-            datetime
-                .timestamp_nanos_opt()
-                .ok_or::<RenderError>(
-                    RenderErrorReason::Other(
-                        "An i64 with nanosecond precision can span a range of ~584 years. This timestamp is out of range.".to_string(),
-                    )
-                    .into(),
-                )?
-                .to_string()
-        } else if let Some(input_rfc3339) = h.hash_get("years_since") {
-            let input_rfc3339 = input_rfc3339.render();
-
-            let base_datetime = DateTime::parse_from_rfc3339(&input_rfc3339)
-                .map_err(|e| {
-                    <RenderErrorReason as Into<RenderError>>::into(RenderErrorReason::Other(format!(
-                        "Invalid RFC3339 datetime format: {}",
-                        e
-                    )))
-                })?
-                .to_utc();
+        out.write(&self.recover(output)?.render())?;
 
-            datetime
-                .years_since(base_datetime.into())
-                .ok_or::<RenderError>(RenderErrorReason::Other("Negative range, try swapping the parameters.".to_string()).into())?
-                .to_string()
-        } else {
-            // DEFAULT to_rfc3339
+        Ok(())
+    }
+}
 
-            datetime.to_rfc3339()
-        };
+/// Applies the years, quarters and months of the `add_*` (sign `1`) or the `sub_*` (sign `-1`) modifiers
+fn shift_months(
+    datetime: DateTime<FixedOffset>,
+    shift: &Shift,
+    sign: i64,
+    month_overflow: MonthOverflow,
+) -> Result<DateTime<FixedOffset>, String> {
+    let datetime = if let Some(years) = shift.years {
+        calendar::add_months(datetime, years.saturating_mul(12 * sign), month_overflow)
+            .ok_or_else(|| "Years parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(quarters) = shift.quarters {
+        calendar::add_months(datetime, quarters.saturating_mul(3 * sign), month_overflow)
+            .ok_or_else(|| "Quarters parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(months) = shift.months {
+        calendar::add_months(datetime, months.saturating_mul(sign), month_overflow)
+            .ok_or_else(|| "Months parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    Ok(datetime)
+}
 
-        out.write(&output)?;
+/// Applies the rest of the `add_*` (sign `1`) or the `sub_*` (sign `-1`) modifiers, weeks and below
+fn shift(datetime: DateTime<FixedOffset>, shift: &Shift, sign: i64, business_days: &BusinessDays) -> Result<DateTime<FixedOffset>, String> {
+    let datetime = if let Some(weeks) = shift.weeks {
+        signed(datetime, weeks, sign).ok_or_else(|| "Weeks parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(days) = shift.days {
+        calendar::add_days(datetime, days.saturating_mul(sign))
+            .ok_or_else(|| "Days parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(days) = shift.business_days {
+        business_days
+            .calendar_offset(datetime.date_naive(), days.saturating_mul(sign))
+            .and_then(|offset| calendar::add_days(datetime, offset))
+            .ok_or_else(|| "Business days parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(hours) = shift.hours {
+        signed(datetime, hours, sign).ok_or_else(|| "Hours parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(min) = shift.minutes {
+        signed(datetime, min, sign).ok_or_else(|| "Minutes parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(sec) = shift.seconds {
+        signed(datetime, sec, sign).ok_or_else(|| "Seconds parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(msec) = shift.milliseconds {
+        signed(datetime, msec, sign).ok_or_else(|| "Milli-seconds parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(usec) = shift.microseconds {
+        signed(datetime, usec, sign).ok_or_else(|| "Micro-seconds parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    let datetime = if let Some(nsec) = shift.nanoseconds {
+        signed(datetime, nsec, sign).ok_or_else(|| "Nano-seconds parameter out of range or produces invalid date".to_string())?
+    } else {
+        datetime
+    };
+
+    Ok(datetime)
+}
 
-        Ok(())
+/// Adds (sign `1`) or subtracts (sign `-1`) a `TimeDelta`
+fn signed(datetime: DateTime<FixedOffset>, delta: TimeDelta, sign: i64) -> Option<DateTime<FixedOffset>> {
+    if sign < 0 {
+        datetime.checked_sub_signed(delta)
+    } else {
+        datetime.checked_add_signed(delta)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{NaiveDate, NaiveDateTime};
+    #[cfg(feature = "locale")]
+    use chrono::Locale;
+    use chrono::{Days, Local, Months, NaiveDate, NaiveDateTime};
+    #[cfg(feature = "timezone")]
+    use chrono_tz::Tz;
 
     #[test]
     fn it_works() {
         use handlebars::Handlebars;
 
         let mut h = Handlebars::new();
-        h.register_helper("datetime", Box::new(HandlebarsChronoDateTime));
+        h.register_helper("datetime", Box::new(HandlebarsChronoDateTime::default()));
 
         // default: Utc::now() -> to_rfc3339
         assert_eq!(
@@ -1852,43 +1979,114 @@ mod tests {
             "Failed to render years since from %Y-%m-%d %H:%M:%S string"
         );
 
-        // modifiers
-
-        #[cfg(feature = "timezone")]
-        let comparison = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00")
-            .unwrap()
-            .to_utc()
-            .with_timezone(&Tz::America__Edmonton)
-            .to_rfc3339();
-        #[cfg(feature = "timezone")]
+        // from_rrule + dtstart (+ after): next occurrence
         assert_eq!(
             h.render_template(
-                r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" with_timezone="America/Edmonton"}}"#,
+                r#"{{datetime from_rrule="FREQ=MONTHLY;BYDAY=-1FR" dtstart="2024-01-01T09:00:00Z" after="2024-02-01T00:00:00Z" output_format="%F %R"}}"#,
                 &String::new()
             )
             .expect("Render error"),
-            comparison,
-            "Failed to render RFC3339 from RFC3339 with timezone America/Edmonton"
+            "2024-02-23 09:00",
+            "Failed to render next occurrence of recurrence rule"
         );
 
-        let comparison = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00")
-            .unwrap()
-            .to_utc()
-            .with_timezone(&FixedOffset::west_opt(6 * 3600).unwrap())
-            .to_rfc3339();
         assert_eq!(
             h.render_template(
-                r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" with_timezone="-06:00"}}"#,
+                r#"{{datetime from_rrule="FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=18" dtstart="2024-01-01T00:00:00+02:00" after="2024-01-08T18:00:00+02:00" with_timezone="+02:00" add_days=1}}"#,
                 &String::new()
             )
             .expect("Render error"),
-            comparison,
-            "Failed to render RFC3339 from RFC3339 with fixed offset -06:00"
+            "2024-01-11T18:00:00+02:00",
+            "Failed to render next occurrence of recurrence rule in timezone, with modifiers"
         );
 
-        let comparison = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00")
-            .unwrap()
-            .to_utc()
+        let occurrence = DateTime::parse_from_rfc3339(
+            &h.render_template(
+                r#"{{datetime from_rrule="FREQ=HOURLY" dtstart="2024-01-01T00:00:00Z"}}"#,
+                &String::new(),
+            )
+            .expect("Render error"),
+        )
+        .expect("Parse error");
+        assert!(
+            occurrence > Utc::now() && occurrence <= Utc::now() + TimeDelta::hours(1),
+            "Failed to render next occurrence of recurrence rule after now"
+        );
+
+        // from_cron (+ occurrence + after): next or previous scheduled run
+        for (template, comparison) in [
+            (
+                r#"{{datetime from_cron="0 9 * * MON-FRI" after="2024-01-05T10:00:00Z" output_format="%a %F %R"}}"#,
+                "Mon 2024-01-08 09:00",
+            ),
+            (
+                r#"{{datetime from_cron="0 9 * * MON-FRI" occurrence="previous" after="2024-01-05T10:00:00Z" output_format="%a %F %R"}}"#,
+                "Fri 2024-01-05 09:00",
+            ),
+            (
+                r#"{{datetime from_cron="@daily" after="2024-01-05T10:00:00+02:00" with_timezone="+02:00"}}"#,
+                "2024-01-06T00:00:00+02:00",
+            ),
+            (
+                r#"{{datetime from_cron="30 0 12 * * *" after="2024-01-05T12:00:00Z" add_minutes=5 to_timestamp=true}}"#,
+                "1704456330",
+            ),
+        ] {
+            assert_eq!(
+                h.render_template(template, &String::new()).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+
+        let occurrence = DateTime::parse_from_rfc3339(
+            &h.render_template(r#"{{datetime from_cron="*/5 * * * *"}}"#, &String::new())
+                .expect("Render error"),
+        )
+        .expect("Parse error");
+        assert!(
+            occurrence > Utc::now() && occurrence <= Utc::now() + TimeDelta::minutes(5),
+            "Failed to render next occurrence of cron expression after now"
+        );
+
+        // modifiers
+
+        #[cfg(feature = "timezone")]
+        let comparison = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00")
+            .unwrap()
+            .to_utc()
+            .with_timezone(&Tz::America__Edmonton)
+            .to_rfc3339();
+        #[cfg(feature = "timezone")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" with_timezone="America/Edmonton"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            comparison,
+            "Failed to render RFC3339 from RFC3339 with timezone America/Edmonton"
+        );
+
+        let comparison = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00")
+            .unwrap()
+            .to_utc()
+            .with_timezone(&FixedOffset::west_opt(6 * 3600).unwrap())
+            .to_rfc3339();
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" with_timezone="-06:00"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            comparison,
+            "Failed to render RFC3339 from RFC3339 with fixed offset -06:00"
+        );
+
+        let comparison = DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00")
+            .unwrap()
+            .to_utc()
             .with_timezone(&Local)
             .to_rfc3339();
         assert_eq!(
@@ -2367,149 +2565,709 @@ mod tests {
             comparison,
             "Failed to render timestamp from timestamp minus 123456789 nano-seconds"
         );
-    }
 
-    #[test]
-    fn it_craps() {
-        use handlebars::Handlebars;
+        let comparison = DateTime::from_timestamp(618658211, 0)
+            .unwrap()
+            .checked_add_months(Months::new(14))
+            .unwrap()
+            .checked_add_days(Days::new(3))
+            .unwrap()
+            .checked_add_signed(TimeDelta::try_hours(4).unwrap())
+            .unwrap()
+            .to_rfc3339();
+        assert_eq!(
+            h.render_template(r#"{{datetime from_timestamp="618658211" add="P1Y2M3DT4H"}}"#, &String::new())
+                .expect("Render error"),
+            comparison,
+            "Failed to render RFC3339 from timestamp plus P1Y2M3DT4H"
+        );
 
-        let mut h = Handlebars::new();
-        h.register_helper("datetime", Box::new(HandlebarsChronoDateTime));
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-01-31T10:00:00Z" add="P1M" output_format="%Y-%m-%d"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-02-29",
+            "Failed to render calendar-correct P1M from the end of January"
+        );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_timestamp="618658ergwerg211" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid timestamp"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" add="-PT1.5S" to_timestamp_millis=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "618658209500",
+            "Failed to render timestamp from timestamp plus -PT1.5S"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_timestamp_millis="618658ergwerg211123" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid timestamp in milli-seconds"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" sub="P14D" to_timestamp=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            (618658211 - 14 * 86400).to_string(),
+            "Failed to render timestamp from timestamp minus P14D"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_timestamp_micros="618658ergwerg211123" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid timestamp in micro-seconds"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" sub="-P2W" to_timestamp=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            (618658211 + 14 * 86400).to_string(),
+            "Failed to render timestamp from timestamp minus -P2W"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_timestamp_micros="12345678901234567890" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid timestamp in micro-seconds"
+        let comparison = DateTime::from_timestamp(618658211, 0)
+            .unwrap()
+            .checked_add_months(Months::new(3 * 12 + 2 * 3))
+            .unwrap()
+            .timestamp()
+            .to_string();
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" add_years="3" add_quarters="2" to_timestamp=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            comparison,
+            "Failed to render timestamp from timestamp plus 3 years and 2 quarters"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_timestamp_nanos="618658ergwerg211123123" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid timestamp in nano-seconds"
+        let comparison = DateTime::from_timestamp(618658211, 0)
+            .unwrap()
+            .checked_sub_months(Months::new(3 * 12 + 2 * 3))
+            .unwrap()
+            .timestamp()
+            .to_string();
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" sub_years="3" sub_quarters="2" to_timestamp=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            comparison,
+            "Failed to render timestamp from timestamp minus 3 years and 2 quarters"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_timestamp_nanos="12345678901234567890" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid timestamp in nano-seconds"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-02-29T10:00:00Z" add_years="1" output_format="%Y-%m-%d"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2025-02-28",
+            "Failed to render clamped Feb 29 plus 1 year"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_rfc2822="Wed, 09 AAA 1989 09:30:11 +0200" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid RFC2822"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-02-29T10:00:00Z" add_years="4" month_overflow="error" output_format="%Y-%m-%d"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2028-02-29",
+            "Failed to render Feb 29 plus 4 years"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_rfc3339="1985_06_16T12_00_00Z" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid RFC3339"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-01-30T10:00:00Z" add_days="1" add="P1M" output_format="%Y-%m-%d"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-03-01",
+            "Failed to render Jan 30 plus 1 month then 1 day"
         );
 
-        assert!(
-            matches!(
-                h.render_template(r#"{{datetime from_str="1985-06-16T12:00:00Z" to_timestamp=true}}"#, &String::new()),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid datetime str and format"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" add_days="-3" to_timestamp=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            (618658211 - 3 * 86400).to_string(),
+            "Failed to render timestamp from timestamp plus -3 days"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_str="1985-06-16T12:00:00" input_format="%Y-%m-%d %H:%M:%S" to_timestamp=true}}"#,
-                    &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid datetime str and format"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" sub_months="-1" output_format="%Y-%m-%d"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "1989-09-09",
+            "Failed to render date from timestamp minus -1 months"
         );
 
-        //
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" add_hours="1.5" to_timestamp=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            (618658211 + 5400).to_string(),
+            "Failed to render timestamp from timestamp plus 1.5 hours"
+        );
 
-        #[cfg(feature = "locale")]
-        assert!(
-            matches!(
-                h.render_template(r#"{{datetime output_format="%A, %B %C" locale="GAGA"}}"#, &String::new()),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid locale"
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" sub_minutes="-.25" add_milliseconds="-0.0005" to_timestamp_nanos=true}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            (618_658_211_000_000_000_i64 + 15_000_000_000 - 500).to_string(),
+            "Failed to render timestamp from timestamp minus -0.25 minutes plus -0.0005 milli-seconds"
         );
 
-        assert!(
-            matches!(
+        for (preset, comparison) in [
+            ("iso8601", "1989-08-09T11:30:11+02:00"),
+            ("iso8601_basic", "19890809T113011+0200"),
+            ("http_date", "Wed, 09 Aug 1989 09:30:11 GMT"),
+            ("rss", "Wed, 09 Aug 1989 11:30:11 +0200"),
+            ("atom", "1989-08-09T11:30:11+02:00"),
+            ("cookie", "Wed, 09 Aug 1989 09:30:11 GMT"),
+            ("sql_datetime", "1989-08-09 11:30:11"),
+            ("icalendar", "19890809T093011Z"),
+        ] {
+            assert_eq!(
                 h.render_template(
-                    r#"{{datetime from_timestamp="618658211" years_since="1985-06-16 12:00:00"}}"#,
+                    &format!(
+                        r#"{{{{datetime from_timestamp="618658211" with_timezone="+02:00" format="{}"}}}}"#,
+                        preset
+                    ),
                     &String::new()
-                ),
-                Err(_e)
-            ),
-            "Failed to produce error with invalid years since base date"
+                )
+                .expect("Render error"),
+                comparison,
+                "Failed to render built-in format preset {}",
+                preset
+            );
+        }
+
+        assert_eq!(
+            h.render_template(r#"{{datetime from_timestamp="618658211" date_style="medium"}}"#, &String::new())
+                .expect("Render error"),
+            "Aug 9, 1989",
+            "Failed to render medium date style"
         );
 
-        assert!(
-            matches!(
-                h.render_template(
-                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" with_timezone="-2500"}}"#,
-                    &String::new()
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" date_style="short" time_style="short"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "8/9/89, 9:30 AM",
+            "Failed to render short date and time style"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" date_style="full" time_style="short" locale="de_DE"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "Mittwoch, 9. August 1989, 09:30",
+            "Failed to render full date and short time style in German"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" date_style="long" locale="fr_FR"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "9 août 1989",
+            "Failed to render long date style in French"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="MMM Do, YYYY" format_syntax="moment"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "Aug 9th, 1989",
+            "Failed to render moment format"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="dd LLL yyyy 'at' HH:mm" format_syntax="luxon"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "09 Aug 1989 at 09:30",
+            "Failed to render Luxon format"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="l, jS \\o\\f F Y (t, L)" format_syntax="php"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "Wednesday, 9th of August 1989 (31, 0)",
+            "Failed to render PHP format"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="%B %@o, %Y (Q%@Q, %@t days)"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "August 9th, 1989 (Q3, 31 days)",
+            "Failed to render extended strftime specifiers"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" add_days="-8" output_format="%A %@o %B" locale="fr_FR"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "mardi 1er août",
+            "Failed to render localized ordinal suffix"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="EEEE d MMMM yyyy" format_syntax="java" locale="fr_FR"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "mercredi 9 août 1989",
+            "Failed to render localized Java format"
+        );
+
+        // 2024-03-13 is a Wednesday
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-03-13T10:00:00Z" next_weekday="monday"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-03-18T10:00:00+00:00",
+            "Failed to render RFC3339 from RFC3339 with next Monday"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-03-13T10:00:00Z" previous_weekday="fri"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-03-08T10:00:00+00:00",
+            "Failed to render RFC3339 from RFC3339 with previous Friday"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-03-13T10:00:00Z" nth_weekday_of_month="2:tuesday"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-03-12T10:00:00+00:00",
+            "Failed to render RFC3339 from RFC3339 with second Tuesday of the month"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-03-13T10:00:00Z" with_month="5" nth_weekday_of_month="last:friday"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-05-31T10:00:00+00:00",
+            "Failed to render RFC3339 from RFC3339 with last Friday of May"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-01-15T10:00:00Z" with_year="2025" with_month="2" nth_weekday_of_month="last:friday"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2025-02-28T10:00:00+00:00",
+            "Failed to render RFC3339 from RFC3339 with last Friday of February 2025"
+        );
+
+        // 2024-05-02 is a Thursday
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-05-02T09:30:00Z" add_business_days="3"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-05-07T09:30:00+00:00",
+            "Failed to render RFC3339 from RFC3339 plus 3 business days"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-05-02T09:30:00Z" sub_business_days="4"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-04-26T09:30:00+00:00",
+            "Failed to render RFC3339 from RFC3339 minus 4 business days"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-05-02T09:30:00Z" add_business_days="1" weekend="fri,sat"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-05-05T09:30:00+00:00",
+            "Failed to render RFC3339 from RFC3339 plus 1 business day with a Friday and Saturday weekend"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-05-02T09:30:00Z" business_days_until="2024-05-13T00:00:00Z"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "7",
+            "Failed to render business days until"
+        );
+
+        for (template, comparison) in [
+            (
+                r#"{{datetime from_rfc3339="1989-08-09T18:05:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00"}}"#,
+                "Today at 6:05 PM",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-08-08T07:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00"}}"#,
+                "Yesterday at 7:00 AM",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-08-09T23:30:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00"}}"#,
+                "Today at 11:30 PM",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-08-07T12:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00"}}"#,
+                "Last Monday at 12:00 PM",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-08-11T12:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00" calendar_next_week="Next %A"}}"#,
+                "Next Friday",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-08-10T12:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00" calendar_next_day="Tomorrow, %H:%M"}}"#,
+                "Tomorrow, 12:00",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-09-01T12:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00"}}"#,
+                "September 1, 1989",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-09-01T12:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00" calendar_same_else="%d/%m/%Y"}}"#,
+                "01/09/1989",
+            ),
+        ] {
+            assert_eq!(
+                h.render_template(template, &String::new()).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+
+        for (template, comparison) in [
+            (
+                r#"{{datetime from_rfc3339="1989-08-09T09:30:11Z" age_since="1951-01-07T00:00:00Z"}}"#,
+                "38 years, 7 months, 2 days",
+            ),
+            (
+                r#"{{datetime from_rfc3339="2024-06-01T00:00:00Z" age_since="2021-02-01T00:00:00Z" age_precision="months" age_separator=" "}}"#,
+                "3 years 4 months",
+            ),
+            (
+                r#"{{datetime from_rfc3339="2025-02-28T18:45:00Z" age_since="2024-02-29T00:00:00Z" age_precision="minutes"}}"#,
+                "1 year, 18 hours, 45 minutes",
+            ),
+            (
+                r#"{{datetime from_rfc3339="1989-08-09T09:30:11Z" age_since="1951-01-07T00:00:00Z" age_output="json"}}"#,
+                r#"{"days":2,"months":7,"years":38}"#,
+            ),
+        ] {
+            assert_eq!(
+                h.render_template(template, &String::new()).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="1989-08-09T09:30:11Z" age_since="1951-01-07T00:00:00Z" locale="fr_FR"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "38 ans, 7 mois et 2 jours",
+            "Failed to render localized age"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{#with (datetime from_rfc3339="1989-08-09T09:30:11Z" age_since="1951-01-07T00:00:00Z" age_output="json")}}{{years}}y {{months}}m{{/with}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "38y 7m",
+            "Failed to expose the age object to a block"
+        );
+
+        assert!(
+            h.render_template(r#"{{datetime to_calendar=true}}"#, &String::new())
+                .expect("Render error")
+                .starts_with("Today at "),
+            "Failed to render calendar time relative to now"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="1989-08-07T12:00:00Z" to_calendar=true calendar_base="1989-08-09T09:30:11+02:00" locale="de_DE"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "Letzten Montag um 12:00",
+            "Failed to render localized calendar time"
+        );
+
+        let mut h = Handlebars::new();
+        h.register_helper(
+            "datetime",
+            Box::new(
+                HandlebarsChronoDateTime::default()
+                    .with_holiday_calendar(crate::MemoryHolidayCalendar::new().with_annual(5, 3).with_annual(5, 6))
+                    .with_format("invoice", "%d.%m.%Y")
+                    .with_format("sql_date", "%Y/%m/%d")
+                    .with_format_syntax(FormatSyntax::Moment),
+            ),
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="D MMMM YYYY"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "9 August 1989",
+            "Failed to render format with the default syntax of the helper"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" output_format="%Y" format_syntax="strftime"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "1989",
+            "Failed to render format with overridden syntax"
+        );
+
+        assert_eq!(
+            h.render_template(r#"{{datetime from_timestamp="618658211" format="invoice"}}"#, &String::new())
+                .expect("Render error"),
+            "09.08.1989",
+            "Failed to render registered format preset"
+        );
+
+        assert_eq!(
+            h.render_template(r#"{{datetime from_timestamp="618658211" format="sql_date"}}"#, &String::new())
+                .expect("Render error"),
+            "1989/08/09",
+            "Failed to render registered format preset overriding a built-in one"
+        );
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_timestamp="618658211" format="http_date" locale="fr_FR"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "Wed, 09 Aug 1989 09:30:11 GMT",
+            "Failed to render built-in format preset in English despite the locale"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-05-02T09:30:00Z" add_business_days="1"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "2024-05-07T09:30:00+00:00",
+            "Failed to render RFC3339 from RFC3339 plus 1 business day with holidays"
+        );
+
+        assert_eq!(
+            h.render_template(
+                r#"{{datetime from_rfc3339="2024-05-02T09:30:00Z" business_days_until="2024-04-29T00:00:00Z"}}"#,
+                &String::new()
+            )
+            .expect("Render error"),
+            "-3",
+            "Failed to render negative business days until with holidays"
+        );
+    }
+
+    #[test]
+    fn it_craps() {
+        use handlebars::Handlebars;
+
+        let mut h = Handlebars::new();
+        h.register_helper("datetime", Box::new(HandlebarsChronoDateTime::default()));
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp="618658ergwerg211" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid timestamp"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp_millis="618658ergwerg211123" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid timestamp in milli-seconds"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp_micros="618658ergwerg211123" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid timestamp in micro-seconds"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp_micros="12345678901234567890" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid timestamp in micro-seconds"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp_nanos="618658ergwerg211123123" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid timestamp in nano-seconds"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp_nanos="12345678901234567890" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid timestamp in nano-seconds"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc2822="Wed, 09 AAA 1989 09:30:11 +0200" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid RFC2822"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1985_06_16T12_00_00Z" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid RFC3339"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime from_str="1985-06-16T12:00:00Z" to_timestamp=true}}"#, &String::new()),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid datetime str and format"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_str="1985-06-16T12:00:00" input_format="%Y-%m-%d %H:%M:%S" to_timestamp=true}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid datetime str and format"
+        );
+
+        //
+
+        #[cfg(feature = "locale")]
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime output_format="%A, %B %C" locale="GAGA"}}"#, &String::new()),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid locale"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_timestamp="618658211" years_since="1985-06-16 12:00:00"}}"#,
+                    &String::new()
+                ),
+                Err(_e)
+            ),
+            "Failed to produce error with invalid years since base date"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" with_timezone="-2500"}}"#,
+                    &String::new()
                 ),
                 Err(_e),
             ),
@@ -2845,5 +3603,366 @@ mod tests {
             ),
             "Failed to produce error with invalid nanoseconds"
         );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add="1 month"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid duration"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" sub="P1.5D"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid duration"
+        );
+
+        for template in [
+            r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" sub="P-9223372036854775808Y"}}"#,
+            r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add="-P-9223372036854775808D"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &String::new()), Err(_e),),
+                "Failed to produce error with out of range duration {}",
+                template
+            );
+        }
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_business_days="2.5"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid business days"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_business_days="2" weekend="caturday"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid weekend"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_business_days="2" weekend="mon,tue,wed,thu,fri,sat,sun"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error without any business days"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" business_days_until="tomorrow"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid business days until"
+        );
+
+        for template in [
+            r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_business_days="100000000"}}"#,
+            r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" business_days_until="9999-12-31T00:00:00Z"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &String::new()), Err(_e),),
+                "Failed to produce error with too wide business days range {}",
+                template
+            );
+        }
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" to_calendar=true calendar_base="tomorrow"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid calendar base"
+        );
+
+        for template in [
+            r#"{{datetime from_rfc3339="1989-08-09T09:30:11Z" age_since="2024-01-01T00:00:00Z"}}"#,
+            r#"{{datetime age_since="yesterday"}}"#,
+            r#"{{datetime age_since="1951-01-07T00:00:00Z" age_precision="weeks"}}"#,
+            r#"{{datetime age_since="1951-01-07T00:00:00Z" age_output="xml"}}"#,
+            r#"{{datetime from_rfc3339="1989-08-09T09:30:11Z" age_since="1951-01-07T00:00:00Z" locale="bg_BG"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &String::new()), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+
+        for template in [
+            r#"{{datetime from_rrule="FREQ=SOMETIMES" dtstart="2024-01-01T00:00:00Z"}}"#,
+            r#"{{datetime from_rrule="FREQ=DAILY"}}"#,
+            r#"{{datetime from_rrule="FREQ=DAILY" dtstart="yesterday"}}"#,
+            r#"{{datetime from_rrule="FREQ=DAILY" dtstart="2024-01-01T00:00:00Z" after="tomorrow"}}"#,
+            r#"{{datetime from_rrule="FREQ=DAILY;COUNT=2" dtstart="2024-01-01T00:00:00Z" after="2024-01-02T00:00:00Z"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &String::new()), Err(_e),),
+                "Failed to produce error with recurrence rule {}",
+                template
+            );
+        }
+
+        for template in [
+            r#"{{datetime from_cron="0 9 * *"}}"#,
+            r#"{{datetime from_cron="0 9 * * SOMEDAY"}}"#,
+            r#"{{datetime from_cron="@reboot"}}"#,
+            r#"{{datetime from_cron="0 9 * * *" occurrence="last"}}"#,
+            r#"{{datetime from_cron="0 9 * * *" after="tomorrow"}}"#,
+            r#"{{datetime from_cron="0 0 30 2 *"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &String::new()), Err(_e),),
+                "Failed to produce error with cron expression {}",
+                template
+            );
+        }
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" next_weekday="someday"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid next weekday"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" previous_weekday="someday"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid previous weekday"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" nth_weekday_of_month="first:monday"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid nth weekday of month"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="2024-03-13T10:00:00Z" nth_weekday_of_month="5:monday"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with missing 5th Monday"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_years="many"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid years"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" sub_quarters="many"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid quarters"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_years="1" month_overflow="round"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid month overflow"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="2024-02-29T10:00:00Z" sub_years="1" month_overflow="error"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with non-existent Feb 29"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="2024-01-31T10:00:00Z" add="P1M" month_overflow="error"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with non-existent Feb 31"
+        );
+
+        for template in [
+            r#"{{datetime from_rfc3339="2024-01-31T10:00:00Z" add="P1M" add_days="1" month_overflow="error"}}"#,
+            r#"{{datetime from_rfc3339="2024-01-31T10:00:00Z" sub_days="1" add="P1M" month_overflow="error"}}"#,
+            r#"{{datetime from_rfc3339="2024-01-31T10:00:00Z" add_years="1" sub="P11M" month_overflow="error"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &String::new()), Err(_e),),
+                "Failed to produce error with chained shifts through Feb 31 in {}",
+                template
+            );
+        }
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_days="1.5"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with fractional days"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_hours="1.2.3"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid fractional hours"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" sub_seconds="-"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with sign only seconds"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(
+                    r#"{{datetime from_rfc3339="1989-08-09T09:30:11+02:00" add_hours="99999999999999999999"}}"#,
+                    &String::new()
+                ),
+                Err(_e),
+            ),
+            "Failed to produce error with out of range hours"
+        );
+
+        assert!(
+            matches!(h.render_template(r#"{{datetime format="invoice"}}"#, &String::new()), Err(_e),),
+            "Failed to produce error with unknown format preset"
+        );
+
+        assert!(
+            matches!(h.render_template(r#"{{datetime date_style="tiny"}}"#, &String::new()), Err(_e),),
+            "Failed to produce error with invalid date style"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime date_style="short" time_style="huge"}}"#, &String::new()),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid time style"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime output_format="%Y" format_syntax="cobol"}}"#, &String::new()),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid format syntax"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime output_format="[YYYY" format_syntax="moment"}}"#, &String::new()),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid moment format"
+        );
+
+        assert!(
+            matches!(h.render_template(r#"{{datetime output_format="%Y-%!"}}"#, &String::new()), Err(_e),),
+            "Failed to produce error with invalid strftime output format"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime from_str="1989" input_format="%!"}}"#, &String::new()),
+                Err(_e),
+            ),
+            "Failed to produce error with invalid strftime input format"
+        );
+
+        assert!(
+            HandlebarsChronoDateTime::default().try_with_format("broken", "%Y-%!").is_err(),
+            "Failed to reject invalid format preset"
+        );
+        assert!(
+            std::panic::catch_unwind(|| HandlebarsChronoDateTime::default().with_format("broken", "%Y-%!")).is_err(),
+            "Failed to panic with invalid format preset"
+        );
+
+        assert!(
+            matches!(
+                h.render_template(r#"{{datetime from_timestap="618658211"}}"#, &String::new()),
+                Err(_e),
+            ),
+            "Failed to produce error with unknown hash parameter"
+        );
     }
 }
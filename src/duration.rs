@@ -0,0 +1,507 @@
+use chrono::TimeDelta;
+use handlebars::{Context, Handlebars, Helper, HelperDef, JsonRender, RenderContext, RenderError, ScopedJson};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::companions::instant;
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::iso_duration::IsoDuration;
+use crate::spec::{parse_fractional_delta, NANOS_PER_MILLISECOND, NANOS_PER_SECOND};
+use crate::styles;
+
+/// Unit of a duration breakdown, from the smallest to the largest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Unit {
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+}
+
+impl Unit {
+    /// Every unit, from the largest to the smallest
+    const ALL: [Unit; 6] = [
+        Unit::Weeks,
+        Unit::Days,
+        Unit::Hours,
+        Unit::Minutes,
+        Unit::Seconds,
+        Unit::Milliseconds,
+    ];
+
+    fn millis(self) -> u64 {
+        match self {
+            Self::Milliseconds => 1,
+            Self::Seconds => 1_000,
+            Self::Minutes => 60_000,
+            Self::Hours => 3_600_000,
+            Self::Days => 86_400_000,
+            Self::Weeks => 604_800_000,
+        }
+    }
+
+    fn abbreviation(self) -> &'static str {
+        match self {
+            Self::Milliseconds => "ms",
+            Self::Seconds => "s",
+            Self::Minutes => "m",
+            Self::Hours => "h",
+            Self::Days => "d",
+            Self::Weeks => "w",
+        }
+    }
+
+    /// Name of the unit in the language of the locale, English without a locale
+    ///
+    /// `None` for languages without bundled names, see [`LANGUAGES`].
+    pub(crate) fn name(self, count: u64, locale: Option<&str>) -> Option<&'static str> {
+        let language = styles::language(locale);
+        // French uses the singular for 0 too
+        let one = count == 1 || (count == 0 && language == "fr");
+
+        let (singular, plural) = match (language, self) {
+            ("de", Self::Milliseconds) => ("Millisekunde", "Millisekunden"),
+            ("de", Self::Seconds) => ("Sekunde", "Sekunden"),
+            ("de", Self::Minutes) => ("Minute", "Minuten"),
+            ("de", Self::Hours) => ("Stunde", "Stunden"),
+            ("de", Self::Days) => ("Tag", "Tage"),
+            ("de", Self::Weeks) => ("Woche", "Wochen"),
+            ("fr", Self::Milliseconds) => ("milliseconde", "millisecondes"),
+            ("fr", Self::Seconds) => ("seconde", "secondes"),
+            ("fr", Self::Minutes) => ("minute", "minutes"),
+            ("fr", Self::Hours) => ("heure", "heures"),
+            ("fr", Self::Days) => ("jour", "jours"),
+            ("fr", Self::Weeks) => ("semaine", "semaines"),
+            ("es", Self::Milliseconds) => ("milisegundo", "milisegundos"),
+            ("es", Self::Seconds) => ("segundo", "segundos"),
+            ("es", Self::Minutes) => ("minuto", "minutos"),
+            ("es", Self::Hours) => ("hora", "horas"),
+            ("es", Self::Days) => ("día", "días"),
+            ("es", Self::Weeks) => ("semana", "semanas"),
+            ("it", Self::Milliseconds) => ("millisecondo", "millisecondi"),
+            ("it", Self::Seconds) => ("secondo", "secondi"),
+            ("it", Self::Minutes) => ("minuto", "minuti"),
+            ("it", Self::Hours) => ("ora", "ore"),
+            ("it", Self::Days) => ("giorno", "giorni"),
+            ("it", Self::Weeks) => ("settimana", "settimane"),
+            ("pt", Self::Milliseconds) => ("milissegundo", "milissegundos"),
+            ("pt", Self::Seconds) => ("segundo", "segundos"),
+            ("pt", Self::Minutes) => ("minuto", "minutos"),
+            ("pt", Self::Hours) => ("hora", "horas"),
+            ("pt", Self::Days) => ("dia", "dias"),
+            ("pt", Self::Weeks) => ("semana", "semanas"),
+            ("nl", Self::Milliseconds) => ("milliseconde", "milliseconden"),
+            ("nl", Self::Seconds) => ("seconde", "seconden"),
+            ("nl", Self::Minutes) => ("minuut", "minuten"),
+            ("nl", Self::Hours) => ("uur", "uur"),
+            ("nl", Self::Days) => ("dag", "dagen"),
+            ("nl", Self::Weeks) => ("week", "weken"),
+            ("en", Self::Milliseconds) => ("millisecond", "milliseconds"),
+            ("en", Self::Seconds) => ("second", "seconds"),
+            ("en", Self::Minutes) => ("minute", "minutes"),
+            ("en", Self::Hours) => ("hour", "hours"),
+            ("en", Self::Days) => ("day", "days"),
+            ("en", Self::Weeks) => ("week", "weeks"),
+            _ => return None,
+        };
+
+        Some(if one { singular } else { plural })
+    }
+}
+
+/// Languages with bundled unit names, for the `long` duration style and the age text
+pub(crate) const LANGUAGES: [&str; 7] = ["en", "de", "fr", "es", "it", "pt", "nl"];
+
+/// Joins the parts of a breakdown as a list in the language of the locale, as in `2 Tage und 3 Stunden`
+///
+/// English keeps a comma before the last part, as in `1 hour, 23 minutes, 45 seconds`.
+pub(crate) fn join(parts: &[String], locale: Option<&str>) -> String {
+    let last = match styles::language(locale) {
+        "de" => " und ",
+        "fr" => " et ",
+        "es" => " y ",
+        "it" | "pt" => " e ",
+        "nl" => " en ",
+        _ => ", ",
+    };
+
+    match parts.split_last() {
+        Some((tail, init)) if !init.is_empty() => format!("{}{}{}", init.join(", "), last, tail),
+        _ => parts.concat(),
+    }
+}
+
+/// Error of a locale without bundled unit names
+pub(crate) fn unsupported(locale: Option<&str>) -> String {
+    format!(
+        "No unit names for the locale {}. Supported languages are {}",
+        locale.unwrap_or_default(),
+        LANGUAGES.join(", ")
+    )
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "milliseconds" | "millisecond" | "ms" => Ok(Self::Milliseconds),
+            "seconds" | "second" | "s" => Ok(Self::Seconds),
+            "minutes" | "minute" | "m" => Ok(Self::Minutes),
+            "hours" | "hour" | "h" => Ok(Self::Hours),
+            "days" | "day" | "d" => Ok(Self::Days),
+            "weeks" | "week" | "w" => Ok(Self::Weeks),
+            _ => Err(format!(
+                "Unknown duration unit: {}. Supported values are weeks, days, hours, minutes, seconds and milliseconds",
+                s
+            )),
+        }
+    }
+}
+
+/// Output style of the `duration` helper
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Style {
+    /// `1h 23m 45s`
+    #[default]
+    Compact,
+    /// `01:23:45`
+    Clock,
+    /// `PT1H23M45S`
+    Iso,
+    /// `1 hour, 23 minutes, 45 seconds`
+    Long,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "compact" => Ok(Self::Compact),
+            "clock" => Ok(Self::Clock),
+            "iso" | "iso8601" => Ok(Self::Iso),
+            "long" => Ok(Self::Long),
+            _ => Err(format!(
+                "Unknown duration style: {}. Supported values are compact, clock, iso and long",
+                s
+            )),
+        }
+    }
+}
+
+/// Formats an exact duration, broken down into the units from `largest` down to `smallest`
+///
+/// The remainder below the smallest unit is truncated. The `clock` style breaks down into hours at most and always
+/// shows the minutes, unless the largest unit is the seconds. The `long` style fails for languages without unit names.
+pub(crate) fn format(delta: TimeDelta, style: Style, largest: Unit, smallest: Unit, locale: Option<&str>) -> Result<String, String> {
+    if largest < smallest {
+        return Err("The largest unit of a duration can't be smaller than the smallest one".to_string());
+    }
+    if style == Style::Clock && largest > Unit::Hours {
+        return Err("The largest unit of the clock duration style can't be larger than hours".to_string());
+    }
+
+    let sign = if delta < TimeDelta::zero() { "-" } else { "" };
+    let millis = delta.num_milliseconds().unsigned_abs();
+
+    let smallest = if style == Style::Clock {
+        smallest.min(Unit::Minutes).min(largest)
+    } else {
+        smallest
+    };
+
+    let mut remaining = millis;
+    let parts = Unit::ALL
+        .into_iter()
+        .filter(|unit| (smallest..=largest).contains(unit))
+        .map(|unit| {
+            let value = remaining / unit.millis();
+            remaining %= unit.millis();
+
+            (unit, value)
+        })
+        .collect::<Vec<_>>();
+
+    let output = match style {
+        Style::Compact => {
+            let output = parts
+                .iter()
+                .filter(|(_unit, value)| *value > 0)
+                .map(|(unit, value)| format!("{}{}", value, unit.abbreviation()))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            if output.is_empty() {
+                format!("0{}", smallest.abbreviation())
+            } else {
+                output
+            }
+        }
+        Style::Clock => parts
+            .iter()
+            .enumerate()
+            .map(|(i, (unit, value))| match (i, unit) {
+                (0, _) => format!("{:02}", value),
+                (_, Unit::Milliseconds) => format!(".{:03}", value),
+                _ => format!(":{:02}", value),
+            })
+            .collect(),
+        Style::Iso => {
+            let value = |unit| parts.iter().find(|(u, _value)| *u == unit).map_or(0, |(_unit, value)| *value);
+
+            let mut date = String::new();
+            for (unit, designator) in [(Unit::Weeks, 'W'), (Unit::Days, 'D')] {
+                if value(unit) > 0 {
+                    date.push_str(&format!("{}{}", value(unit), designator));
+                }
+            }
+
+            let mut time = String::new();
+            for (unit, designator) in [(Unit::Hours, 'H'), (Unit::Minutes, 'M')] {
+                if value(unit) > 0 {
+                    time.push_str(&format!("{}{}", value(unit), designator));
+                }
+            }
+            match (value(Unit::Seconds), value(Unit::Milliseconds)) {
+                (0, 0) => {}
+                (seconds, 0) => time.push_str(&format!("{}S", seconds)),
+                (seconds, millis) => time.push_str(&format!("{}.{}S", seconds, format!("{:03}", millis).trim_end_matches('0'))),
+            }
+
+            match (date.is_empty(), time.is_empty()) {
+                (true, true) => "PT0S".to_string(),
+                (_, true) => format!("P{}", date),
+                _ => format!("P{}T{}", date, time),
+            }
+        }
+        Style::Long => {
+            let name = |unit: Unit, value| unit.name(value, locale).ok_or_else(|| unsupported(locale));
+            let output = parts
+                .iter()
+                .filter(|(_unit, value)| *value > 0)
+                .map(|(unit, value)| Ok(format!("{} {}", value, name(*unit, *value)?)))
+                .collect::<Result<Vec<_>, String>>()?;
+
+            if output.is_empty() {
+                format!("0 {}", name(smallest, 0)?)
+            } else {
+                join(&output, locale)
+            }
+        }
+    };
+
+    Ok(format!("{}{}", sign, output))
+}
+
+/// `duration` helper, formats elapsed time, as in `{{duration seconds=5025}}` or `{{duration job.started job.finished}}`
+///
+/// The duration comes from the `seconds`, `milliseconds` or `iso` hash parameter, or from the difference between two
+/// date times given as parameters, the second one defaulting to now.
+#[derive(Clone)]
+pub(crate) struct FormatDuration {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl FormatDuration {
+    fn render(&self, h: &Helper) -> Result<String, String> {
+        let hash = |key| h.hash_get(key).map(|value| value.value().render());
+
+        let delta = if let Some(seconds) = hash("seconds") {
+            parse_fractional_delta(&seconds, NANOS_PER_SECOND).map_err(|e| format!("Invalid seconds parameter: {}", e))?
+        } else if let Some(millis) = hash("milliseconds") {
+            parse_fractional_delta(&millis, NANOS_PER_MILLISECOND).map_err(|e| format!("Invalid milliseconds parameter: {}", e))?
+        } else if let Some(iso) = hash("iso") {
+            let duration = iso
+                .parse::<IsoDuration>()
+                .map_err(|e| format!("Invalid ISO 8601 duration: {}", e))?;
+
+            if duration.total_months() != Some(0) {
+                return Err("Years and months of an ISO 8601 duration don't have an exact length".to_string());
+            }
+
+            duration
+                .total_days()
+                .and_then(TimeDelta::try_days)
+                .zip(duration.clock())
+                .and_then(|(days, clock)| days.checked_add(&clock))
+                .ok_or_else(|| "ISO 8601 duration out of range".to_string())?
+        } else if let Some(start) = h.param(0) {
+            let start = instant(&self.helper, Some(start.value()))?;
+            let end = instant(&self.helper, h.param(1).map(|end| end.value()))?;
+
+            end - start
+        } else {
+            return Err("Missing duration, expected `seconds`, `milliseconds`, `iso` or two date times".to_string());
+        };
+
+        let style = hash("style").map(|style| style.parse()).transpose()?.unwrap_or_default();
+        let largest = hash("largest").map(|unit| unit.parse()).transpose()?;
+        let largest = largest.unwrap_or(if style == Style::Clock { Unit::Hours } else { Unit::Days });
+        let smallest = hash("smallest").map(|unit| unit.parse()).transpose()?.unwrap_or(Unit::Seconds);
+
+        let locale = hash("locale");
+        let locale = self.helper.locale(locale.as_deref());
+
+        #[cfg(not(feature = "locale"))]
+        if let Some(locale) = locale {
+            return Err(format!(
+                "You need to enable the `locale` feature of `handlebars-chrono` for the `locale`={} param to work.",
+                locale
+            ));
+        }
+
+        format(delta, style, largest, smallest, locale)
+    }
+}
+
+impl HelperDef for FormatDuration {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        Ok(self.helper.recover(self.render(h))?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_works() {
+        let delta = TimeDelta::seconds(5025);
+
+        assert_eq!(
+            format(delta, Style::Compact, Unit::Days, Unit::Seconds, None),
+            Ok("1h 23m 45s".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Compact, Unit::Days, Unit::Minutes, None),
+            Ok("1h 23m".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Compact, Unit::Minutes, Unit::Minutes, None),
+            Ok("83m".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Clock, Unit::Hours, Unit::Seconds, None),
+            Ok("01:23:45".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Iso, Unit::Days, Unit::Seconds, None),
+            Ok("PT1H23M45S".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Long, Unit::Days, Unit::Minutes, None),
+            Ok("1 hour, 23 minutes".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::hours(51), Style::Long, Unit::Days, Unit::Seconds, Some("de_DE")),
+            Ok("2 Tage und 3 Stunden".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Long, Unit::Days, Unit::Seconds, Some("es_ES")),
+            Ok("1 hora, 23 minutos y 45 segundos".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::hours(51), Style::Clock, Unit::Hours, Unit::Minutes, None),
+            Ok("51:00".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Clock, Unit::Minutes, Unit::Seconds, None),
+            Ok("83:45".to_string())
+        );
+        assert_eq!(
+            format(delta, Style::Clock, Unit::Seconds, Unit::Milliseconds, None),
+            Ok("5025.000".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::milliseconds(-90_500), Style::Iso, Unit::Weeks, Unit::Milliseconds, None),
+            Ok("-PT1M30.5S".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::days(9), Style::Iso, Unit::Weeks, Unit::Seconds, None),
+            Ok("P1W2D".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::zero(), Style::Compact, Unit::Days, Unit::Seconds, None),
+            Ok("0s".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::zero(), Style::Iso, Unit::Days, Unit::Seconds, None),
+            Ok("PT0S".to_string())
+        );
+        assert_eq!(
+            format(TimeDelta::zero(), Style::Long, Unit::Days, Unit::Minutes, Some("fr")),
+            Ok("0 minute".to_string())
+        );
+
+        let mut h = Handlebars::new();
+        h.register_helper(
+            "duration",
+            Box::new(FormatDuration {
+                helper: Arc::new(HandlebarsChronoDateTime::default()),
+            }),
+        );
+
+        let data = json!({"started": 618658211, "finished": "1989-08-09T10:53:56Z"});
+        for (template, comparison) in [
+            ("{{duration seconds=5025}}", "1h 23m 45s"),
+            (r#"{{duration milliseconds="1500" style="iso" smallest="ms"}}"#, "PT1.5S"),
+            (r#"{{duration iso="P1DT2H" style="long" largest="hours"}}"#, "26 hours"),
+            (r#"{{duration started finished style="clock"}}"#, "01:23:45"),
+            (r#"{{duration seconds=5025 style="clock" largest="minutes"}}"#, "83:45"),
+            (r#"{{duration finished started smallest="minutes"}}"#, "-1h 23m"),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!(format(TimeDelta::zero(), Style::Compact, Unit::Minutes, Unit::Hours, None).is_err());
+        assert!(format(TimeDelta::hours(51), Style::Clock, Unit::Days, Unit::Minutes, None).is_err());
+        assert!(format(TimeDelta::hours(51), Style::Long, Unit::Days, Unit::Minutes, Some("bg_BG")).is_err());
+        assert_eq!(
+            format(TimeDelta::hours(51), Style::Compact, Unit::Days, Unit::Minutes, Some("bg_BG")),
+            Ok("2d 3h".to_string())
+        );
+        assert!("fortnights".parse::<Unit>().is_err());
+        assert!("verbose".parse::<Style>().is_err());
+
+        let mut h = Handlebars::new();
+        h.register_helper(
+            "duration",
+            Box::new(FormatDuration {
+                helper: Arc::new(HandlebarsChronoDateTime::default()),
+            }),
+        );
+
+        for template in [
+            "{{duration}}",
+            r#"{{duration seconds="a while"}}"#,
+            r#"{{duration iso="P1M"}}"#,
+            r#"{{duration seconds=60 style="verbose"}}"#,
+            r#"{{duration seconds=60 largest="seconds" smallest="hours"}}"#,
+            r#"{{duration seconds=60 style="clock" largest="days"}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &json!({})), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use chrono::{DateTime, FixedOffset};
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, JsonTruthy, JsonValue, Output, RenderContext};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::blocks::render_or_recover;
+use crate::calendar::MonthOverflow;
+use crate::companions::{instant, parts};
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::iso_duration::IsoDuration;
+
+/// Upper bound of iterations, so a tiny step over a wide range can't render forever
+const MAX_STEPS: i64 = 10_000;
+
+/// Step between the date times of an iteration, a calendar unit or an ISO 8601 duration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Step(pub(crate) IsoDuration);
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let duration = match s.trim().to_lowercase().as_str() {
+            "year" | "years" => "P1Y",
+            "quarter" | "quarters" => "P3M",
+            "month" | "months" => "P1M",
+            "week" | "weeks" => "P1W",
+            "day" | "days" => "P1D",
+            "hour" | "hours" => "PT1H",
+            "minute" | "minutes" => "PT1M",
+            _ => s,
+        };
+
+        duration.parse().map(Self).map_err(|e| format!("Invalid step parameter: {}", e))
+    }
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self(IsoDuration {
+            days: 1,
+            ..IsoDuration::default()
+        })
+    }
+}
+
+impl Step {
+    /// Date times from `start` up to and including `end`
+    ///
+    /// Every date time is computed from `start`, so stepping by months from Jan 31 gives Feb 29 then Mar 31.
+    pub fn between(&self, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> Result<Vec<DateTime<FixedOffset>>, String> {
+        let nth = |n| {
+            self.0
+                .scaled(n)
+                .and_then(|duration| duration.add_to(start, MonthOverflow::Clamp))
+                .ok_or_else(|| "Step out of range or produces invalid date".to_string())
+        };
+
+        if start <= end && nth(1)? <= start {
+            return Err("Step parameter must move forward in time".to_string());
+        }
+
+        let mut dates = Vec::new();
+        for n in 0.. {
+            let datetime = nth(n)?;
+            if datetime > end {
+                break;
+            }
+
+            if n == MAX_STEPS {
+                return Err(format!("Too many steps, at most {} date times can be iterated", MAX_STEPS));
+            }
+
+            dates.push(datetime);
+        }
+
+        Ok(dates)
+    }
+}
+
+/// `each_date` block helper, iterates from a start to an end date time, as in `{{#each_date start end step="week"}}`
+///
+/// Both parameters are UNIX timestamps in seconds, RFC3339 or RFC2822 date times. Each step is exposed as `this`,
+/// RFC3339 by default, or as an object of fields with `to_parts=true`.
+#[derive(Clone)]
+pub(crate) struct EachDate {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl EachDate {
+    fn items(&self, h: &Helper) -> Result<Vec<JsonValue>, String> {
+        let (start, end) = match (h.param(0), h.param(1)) {
+            (Some(start), Some(end)) => (
+                instant(&self.helper, Some(start.value()))?,
+                instant(&self.helper, Some(end.value()))?,
+            ),
+            _ => return Err("Missing date time parameters of `each_date`, expected the start and the end".to_string()),
+        };
+
+        let step = h
+            .hash_get("step")
+            .map(|step| step.value().render().parse::<Step>())
+            .transpose()?
+            .unwrap_or_default();
+
+        let to_parts = h.hash_get("to_parts").is_some_and(|to_parts| to_parts.value().is_truthy(false));
+
+        Ok(step
+            .between(start, end)?
+            .iter()
+            .map(|datetime| {
+                if to_parts {
+                    parts(datetime)
+                } else {
+                    JsonValue::String(datetime.to_rfc3339())
+                }
+            })
+            .collect())
+    }
+}
+
+impl HelperDef for EachDate {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        render_or_recover(&self.helper, self.items(h), h, r, ctx, rc, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+    use serde_json::json;
+
+    #[test]
+    fn it_works() {
+        let h = registry();
+        let data = json!({"start": "1989-08-09T09:30:11Z", "end": "1989-08-12T09:30:11Z"});
+
+        for (template, comparison) in [
+            (
+                "{{#each_date start end}}{{@index}}:{{this}}{{#unless @last}},{{/unless}}{{/each_date}}",
+                "0:1989-08-09T09:30:11+00:00,1:1989-08-10T09:30:11+00:00,2:1989-08-11T09:30:11+00:00,3:1989-08-12T09:30:11+00:00",
+            ),
+            (
+                r#"{{#each_date start end step="P2D" as |date|}}{{datetime from_rfc3339=date output_format="%a"}} {{/each_date}}"#,
+                "Wed Fri ",
+            ),
+            (
+                r#"{{#each_date start end to_parts=true}}{{#if @first}}{{day}}.{{month}}{{/if}}{{#if @last}}-{{this.day}} {{weekday}}{{/if}}{{/each_date}}"#,
+                "9.8-12 6",
+            ),
+            ("{{#each_date end start}}{{this}}{{else}}empty{{/each_date}}", "empty"),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+
+        let date = |datetime| DateTime::parse_from_rfc3339(datetime).expect("Parse error");
+        assert_eq!(
+            "month"
+                .parse::<Step>()
+                .and_then(|step| step.between(date("2024-01-31T00:00:00Z"), date("2024-04-30T00:00:00Z"))),
+            Ok(vec![
+                date("2024-01-31T00:00:00Z"),
+                date("2024-02-29T00:00:00Z"),
+                date("2024-03-31T00:00:00Z"),
+                date("2024-04-30T00:00:00Z"),
+            ])
+        );
+    }
+
+    #[test]
+    fn it_craps() {
+        let h = registry();
+
+        for template in [
+            "{{#each_date 618658211}}{{/each_date}}",
+            r#"{{#each_date 618658211 618658212 step="fortnight"}}{{/each_date}}"#,
+            r#"{{#each_date 618658211 618658212 step="PT0S"}}{{/each_date}}"#,
+            r#"{{#each_date 618658211 618658212 step="-P1D"}}{{/each_date}}"#,
+            r#"{{#each_date 0 618658211 step="PT1M"}}{{/each_date}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &json!({})), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+    }
+}
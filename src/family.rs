@@ -0,0 +1,191 @@
+use handlebars::{Handlebars, HelperDef};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::calendar_month::CalendarMonth;
+use crate::collections::{Collection, Operation};
+use crate::companions::{Compare, Comparison, Diff};
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::duration::FormatDuration;
+use crate::each_date::EachDate;
+use crate::group::GroupByDate;
+use crate::range::FormatRange;
+use crate::rrule::EachOccurrence;
+
+/// Names of the helpers of the family, before prefixing
+const HELPERS: [&str; 15] = [
+    "datetime",
+    "before",
+    "after",
+    "same",
+    "diff",
+    "duration",
+    "daterange",
+    "each_date",
+    "calendar_month",
+    "group_by_date",
+    "sort_by_date",
+    "filter_by_date",
+    "earliest",
+    "latest",
+    "each_occurrence",
+];
+
+/// Registers the whole helper family with default settings, every name prefixed with `prefix`
+///
+/// ```rust
+/// use handlebars::Handlebars;
+/// use serde_json::json;
+///
+/// let mut h = Handlebars::new();
+/// handlebars_chrono::register(&mut h, "dt_");
+///
+/// assert_eq!(h.render_template(r#"{{dt_datetime from_timestamp="618658211" format="sql_date"}}"#, &json!({})).expect("Render error"), "1989-08-09");
+/// assert_eq!(h.render_template(r#"{{#if (dt_before 618658211)}}past{{/if}}"#, &json!({})).expect("Render error"), "past");
+/// ```
+pub fn register(registry: &mut Handlebars, prefix: &str) {
+    HelperFamily::new(HandlebarsChronoDateTime::default())
+        .with_prefix(prefix)
+        .register(registry);
+}
+
+/// The `datetime` helper together with its companions, sharing the same configuration
+///
+/// - `datetime`: [`HandlebarsChronoDateTime`]
+/// - `before`, `after`: whether the first date time is before or after the second one, as in `{{before event.start}}`
+/// - `same`: whether two date times are the same instant, or share the calendar fields up to `unit`, as in `{{same a b unit="day"}}`
+/// - `diff`: the difference between two date times in whole `unit`s, as in `{{diff a b unit="days"}}`
+/// - `daterange`: two date times as a range with the shared parts collapsed, as in `{{daterange a b}}` for `9–11 Aug 1989`
+/// - `duration`: elapsed time as `1h 23m`, `01:23:45`, `PT1H23M` or `1 hour, 23 minutes`, as in `{{duration seconds=5025}}`
+/// - `each_date`: block iterating from a start to an end date time, as in `{{#each_date a b step="week"}}`
+/// - `calendar_month`: block rendered with the weeks and days of a month, as in `{{#calendar_month event.start}}`
+/// - `group_by_date`: block iterating over the items of an array grouped by period, as in `{{#group_by_date posts "published" by="month"}}`
+/// - `sort_by_date`, `filter_by_date`: the items of an array sorted by date time or within a window, as in `(filter_by_date events "start" window="upcoming")`
+/// - `earliest`, `latest`: the item of an array with the earliest or latest date time, as in `{{#with (latest posts "published")}}`
+/// - `each_occurrence`: block iterating over the occurrences of an iCalendar RRULE, as in `{{#each_occurrence rule start limit=5}}`
+///
+/// Companion parameters are UNIX timestamps in seconds, RFC3339 or RFC2822 date times, the second one defaults to now.
+///
+/// ```rust
+/// use handlebars::Handlebars;
+/// use handlebars_chrono::{HandlebarsChronoDateTime, HelperFamily};
+/// use serde_json::json;
+///
+/// let mut h = Handlebars::new();
+/// HelperFamily::new(HandlebarsChronoDateTime::default().with_format("invoice", "%d.%m.%Y"))
+///     .with_name("datetime", "date")
+///     .without("same")
+///     .register(&mut h);
+///
+/// assert_eq!(h.render_template(r#"{{date from_timestamp="618658211" format="invoice"}}"#, &json!({})).expect("Render error"), "09.08.1989");
+/// assert_eq!(h.render_template(r#"{{diff 618658211 "1989-08-11T09:30:11Z" unit="days"}}"#, &json!({})).expect("Render error"), "2");
+/// assert!(h.render_template(r#"{{datetime from_timestamp="618658211"}}"#, &json!({})).is_err());
+/// assert!(h.render_template(r#"{{same 618658211 618658211}}"#, &json!({})).is_err());
+/// ```
+#[derive(Clone)]
+pub struct HelperFamily {
+    helper: Arc<HandlebarsChronoDateTime>,
+    prefix: String,
+    names: HashMap<String, String>,
+    excluded: HashSet<String>,
+}
+
+impl HelperFamily {
+    /// Family sharing the configuration of the given helper, registered under the plain names
+    pub fn new(helper: HandlebarsChronoDateTime) -> Self {
+        Self {
+            helper: Arc::new(helper),
+            prefix: String::new(),
+            names: HashMap::new(),
+            excluded: HashSet::new(),
+        }
+    }
+
+    /// Prefixes every name, as in `dt_` for `dt_datetime`, `dt_before` and so on
+    pub fn with_prefix<P: Into<String>>(mut self, prefix: P) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Registers a helper of the family under an alternate name, which is not prefixed
+    ///
+    /// Useful to avoid clashes with helpers of other crates, unknown helpers are ignored.
+    pub fn with_name<H: Into<String>, N: Into<String>>(mut self, helper: H, name: N) -> Self {
+        self.names.insert(helper.into(), name.into());
+        self
+    }
+
+    /// Leaves a helper of the family out of the registration
+    pub fn without<H: Into<String>>(mut self, helper: H) -> Self {
+        self.excluded.insert(helper.into());
+        self
+    }
+
+    /// Name the given helper of the family gets registered under
+    pub fn name(&self, helper: &str) -> String {
+        self.names
+            .get(helper)
+            .cloned()
+            .unwrap_or_else(|| format!("{}{}", self.prefix, helper))
+    }
+
+    /// Registers the helpers of the family
+    pub fn register(&self, registry: &mut Handlebars) {
+        for helper in HELPERS {
+            if self.excluded.contains(helper) {
+                continue;
+            }
+
+            registry.register_helper(&self.name(helper), self.helper_def(helper));
+        }
+    }
+
+    /// Definition of a helper of the family
+    fn helper_def(&self, helper: &str) -> Box<dyn HelperDef + Send + Sync> {
+        let compare = |comparison| {
+            Box::new(Compare {
+                helper: self.helper.clone(),
+                comparison,
+            })
+        };
+
+        let collection = |operation| {
+            Box::new(Collection {
+                helper: self.helper.clone(),
+                operation,
+            })
+        };
+
+        match helper {
+            "before" => compare(Comparison::Before),
+            "after" => compare(Comparison::After),
+            "same" => compare(Comparison::Same),
+            "diff" => Box::new(Diff {
+                helper: self.helper.clone(),
+            }),
+            "duration" => Box::new(FormatDuration {
+                helper: self.helper.clone(),
+            }),
+            "daterange" => Box::new(FormatRange {
+                helper: self.helper.clone(),
+            }),
+            "each_date" => Box::new(EachDate {
+                helper: self.helper.clone(),
+            }),
+            "calendar_month" => Box::new(CalendarMonth {
+                helper: self.helper.clone(),
+            }),
+            "group_by_date" => Box::new(GroupByDate {
+                helper: self.helper.clone(),
+            }),
+            "sort_by_date" => collection(Operation::Sort),
+            "filter_by_date" => collection(Operation::Filter),
+            "earliest" => collection(Operation::Earliest),
+            "latest" => collection(Operation::Latest),
+            "each_occurrence" => Box::new(EachOccurrence {
+                helper: self.helper.clone(),
+            }),
+            _ => Box::new(self.helper.as_ref().clone()),
+        }
+    }
+}
@@ -0,0 +1,1015 @@
+use chrono::format::{Item, StrftimeItems};
+#[cfg(feature = "locale")]
+use chrono::Locale;
+use chrono::{DateTime, Datelike, FixedOffset, Months, NaiveDate, Timelike};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use crate::styles::{self, Style};
+
+/// Syntax of the `output_format` hash parameter
+///
+/// Formats written for other date libraries are translated to chrono's
+/// [strftime](https://docs.rs/chrono/latest/chrono/format/strftime/index.html) before formatting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FormatSyntax {
+    /// chrono's strftime, ex. `%b %e, %Y`
+    #[default]
+    Strftime,
+    /// [Moment.js](https://momentjs.com/docs/#/displaying/format/) tokens, ex. `MMM Do, YYYY`
+    Moment,
+    /// [Luxon](https://moment.github.io/luxon/#/formatting?id=table-of-tokens) tokens, ex. `LLL d, yyyy`
+    Luxon,
+    /// Java [`DateTimeFormatter`](https://docs.oracle.com/javase/8/docs/api/java/time/format/DateTimeFormatter.html) patterns, ex. `MMM d, yyyy`
+    Java,
+    /// PHP [`date()`](https://www.php.net/manual/en/datetime.format.php) format characters, ex. `D, d M Y H:i:s`
+    ///
+    /// Like PHP, names and ordinal suffixes are always English, whatever the locale.
+    Php,
+}
+
+impl FromStr for FormatSyntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "strftime" => Ok(Self::Strftime),
+            "moment" => Ok(Self::Moment),
+            "luxon" => Ok(Self::Luxon),
+            "java" => Ok(Self::Java),
+            "php" => Ok(Self::Php),
+            other => Err(format!("Unknown format syntax: {}", other)),
+        }
+    }
+}
+
+/// Field computed by the crate instead of chrono
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Field {
+    /// Day of the month with ordinal suffix, ex. `9th`
+    DayOrdinal,
+    /// Month number with ordinal suffix, ex. `8th`
+    MonthOrdinal,
+    /// Quarter, ex. `3`
+    Quarter,
+    /// Quarter with ordinal suffix, ex. `3rd`
+    QuarterOrdinal,
+    /// Quarter in full English words, ex. `3rd quarter`
+    QuarterText,
+    /// Day of the year with ordinal suffix, ex. `221st`
+    DayOfYearOrdinal,
+    /// Ordinal suffix of the day of the month, ex. `th`
+    DaySuffix,
+    /// Day of the year starting from 0, without padding
+    DayOfYear0,
+    /// Number of days in the month, ex. `31`
+    DaysInMonth,
+    /// `1` for leap years, `0` otherwise
+    LeapYear,
+    /// Swatch Internet time, ex. `436`
+    SwatchBeat,
+    /// Offset from UTC in seconds, ex. `7200`
+    OffsetSeconds,
+    /// Offset from UTC, or `Z` for UTC, in the given width: `1` for `+02` (or `+0530`), `2` for `+0200` and
+    /// `3` for `+02:00`
+    OffsetOrZ(u8),
+    /// Two letter English weekday name, ex. `We`
+    WeekdayMin,
+    /// Year of at least 4 digits, signed always (`+1989`) or only when outside of `0000` to `9999` (`1989`, `+10191`)
+    ExpandedYear { signed: bool },
+    /// Hour of the day from 1 to 24, optionally padded to 2 digits
+    Hour24From1 { padded: bool },
+    /// Hour of the half-day from 0 to 11, optionally padded to 2 digits
+    Hour12From0 { padded: bool },
+    /// First `n` digits of the fraction of the second
+    Fraction(u8),
+    /// Milli-seconds of the second, without padding
+    Milliseconds,
+    /// Nano-seconds of the second, without padding
+    Nanoseconds,
+    /// UNIX timestamp in milli-seconds
+    TimestampMillis,
+}
+
+/// A piece of a translated output format
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    Strftime(String),
+    Field(Field),
+}
+
+/// Output format translated from any supported syntax to strftime pieces and crate computed fields
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Translation {
+    pieces: Vec<Piece>,
+}
+
+impl Translation {
+    /// Translates a strftime format, extended with the crate specific `%@` specifiers:
+    ///
+    /// - `%@o`: day of the month with ordinal suffix, ex. `9th`
+    /// - `%@S`: ordinal suffix of the day of the month, ex. `th`
+    /// - `%@Q`: quarter, ex. `3`
+    /// - `%@q`: quarter with ordinal suffix, ex. `3rd`
+    /// - `%@t`: number of days in the month, ex. `31`
+    /// - `%@L`: `1` for leap years, `0` otherwise
+    ///
+    /// Any other `%@` specifier is left to chrono.
+    pub fn strftime(format: &str) -> Self {
+        let mut compiled = Self::default();
+        let mut rest = format;
+
+        while let Some(start) = rest.find('%') {
+            compiled.push_strftime(&rest[..start]);
+            let specifier = &rest[start + 1..];
+
+            if let Some(extended) = specifier.strip_prefix('@') {
+                let mut chars = extended.chars();
+                let field = match chars.next() {
+                    Some('o') => Some(Field::DayOrdinal),
+                    Some('S') => Some(Field::DaySuffix),
+                    Some('Q') => Some(Field::Quarter),
+                    Some('q') => Some(Field::QuarterOrdinal),
+                    Some('t') => Some(Field::DaysInMonth),
+                    Some('L') => Some(Field::LeapYear),
+                    _ => None,
+                };
+
+                if let Some(field) = field {
+                    compiled.push_field(field);
+                    rest = chars.as_str();
+                    continue;
+                }
+            }
+
+            // keep the `%` together with the next character, so `%%@o` stays a literal
+            let len = specifier.chars().next().map_or(0, char::len_utf8);
+            compiled.push_strftime(&rest[start..start + 1 + len]);
+            rest = &specifier[len..];
+        }
+
+        compiled.push_strftime(rest);
+        compiled
+    }
+
+    fn push_strftime(&mut self, format: &str) {
+        if let Some(Piece::Strftime(last)) = self.pieces.last_mut() {
+            last.push_str(format);
+        } else if !format.is_empty() {
+            self.pieces.push(Piece::Strftime(format.to_string()));
+        }
+    }
+
+    fn push_literal(&mut self, literal: &str) {
+        self.push_strftime(&literal.replace('%', "%%"));
+    }
+
+    fn push_field(&mut self, field: Field) {
+        self.pieces.push(Piece::Field(field));
+    }
+}
+
+/// A segment of a compiled output format
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Items(Vec<Item<'static>>),
+    Field(Field),
+}
+
+/// Output format compiled from any supported syntax, rendered without parsing the format again
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CompiledFormat {
+    segments: Vec<Segment>,
+    locale: Option<String>,
+    #[cfg(feature = "locale")]
+    chrono_locale: Option<Locale>,
+}
+
+impl CompiledFormat {
+    /// Compiles a format written in the given syntax
+    ///
+    /// Locale dependent specifiers like `%x` and the ordinal suffixes follow the given locale,
+    /// and are English without one. The PHP syntax ignores the locale, as `date()` does.
+    pub fn compile(format: &str, syntax: FormatSyntax, locale: Option<&str>) -> Result<Self, String> {
+        let locale = locale.filter(|_locale| syntax != FormatSyntax::Php);
+        let translation = match syntax {
+            FormatSyntax::Strftime => Translation::strftime(format),
+            FormatSyntax::Moment => translate_moment(format, locale.unwrap_or("en_US"))?,
+            FormatSyntax::Luxon => translate_luxon(format, locale.unwrap_or("en_US"))?,
+            FormatSyntax::Java => translate_java(format)?,
+            FormatSyntax::Php => translate_php(format)?,
+        };
+
+        #[cfg(feature = "locale")]
+        let chrono_locale = locale
+            .map(|locale| Locale::from_str(locale).map_err(|_e| format!("Invalid locale provided: {}", locale)))
+            .transpose()?;
+
+        let segments = translation
+            .pieces
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Strftime(format) => {
+                    #[cfg(feature = "locale")]
+                    let items = match chrono_locale {
+                        Some(locale) => StrftimeItems::new_with_locale(&format, locale),
+                        None => StrftimeItems::new(&format),
+                    };
+                    #[cfg(not(feature = "locale"))]
+                    let items = StrftimeItems::new(&format);
+
+                    items
+                        .parse_to_owned()
+                        .map(Segment::Items)
+                        .map_err(|e| format!("{} in strftime format: {}", e, format))
+                }
+                Piece::Field(field) => Ok(Segment::Field(field)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            segments,
+            locale: locale.map(str::to_string),
+            #[cfg(feature = "locale")]
+            chrono_locale,
+        })
+    }
+
+    /// Renders the format
+    pub fn render(&self, datetime: &DateTime<FixedOffset>) -> Result<String, String> {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Items(items) => self
+                    .write_items(&mut output, datetime, items)
+                    .map_err(|_e| "Failed to format datetime".to_string())?,
+                Segment::Field(field) => output.push_str(&render_field(*field, datetime, self.locale.as_deref())),
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn write_items(&self, output: &mut String, datetime: &DateTime<FixedOffset>, items: &[Item<'static>]) -> std::fmt::Result {
+        #[cfg(feature = "locale")]
+        if let Some(locale) = self.chrono_locale {
+            return write!(output, "{}", datetime.format_localized_with_items(items.iter(), locale));
+        }
+
+        write!(output, "{}", datetime.format_with_items(items.iter()))
+    }
+}
+
+/// Error produced when registering an invalid output format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError(String);
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Upper bound of cached formats per kind, so formats coming from template data can't grow the cache forever
+const CACHE_CAPACITY: usize = 1024;
+
+type OutputKey = (String, FormatSyntax, Option<String>);
+
+/// Compiled output formats and parsed input formats, shared by the clones of a helper
+#[derive(Debug, Default)]
+pub(crate) struct FormatCache {
+    output: RwLock<HashMap<OutputKey, Arc<CompiledFormat>>>,
+    input: RwLock<HashMap<String, Arc<[Item<'static>]>>>,
+}
+
+impl FormatCache {
+    /// Output format compiled for the given syntax and locale, compiled on first use
+    pub fn output(&self, format: &str, syntax: FormatSyntax, locale: Option<&str>) -> Result<Arc<CompiledFormat>, String> {
+        cached(&self.output, (format.to_string(), syntax, locale.map(str::to_string)), || {
+            CompiledFormat::compile(format, syntax, locale).map(Arc::new)
+        })
+    }
+
+    /// Same as [`FormatCache::output`], with the error wrapped in a [`FormatError`]
+    pub fn validate(&self, format: &str) -> Result<(), FormatError> {
+        self.output(format, FormatSyntax::Strftime, None).map(|_| ()).map_err(FormatError)
+    }
+
+    /// Input format items of `from_str`, parsed on first use
+    pub fn input(&self, format: &str) -> Result<Arc<[Item<'static>]>, String> {
+        cached(&self.input, format.to_string(), || {
+            StrftimeItems::new(format)
+                .parse_to_owned()
+                .map(Arc::from)
+                .map_err(|e| format!("{} in strftime format: {}", e, format))
+        })
+    }
+}
+
+fn cached<K: Hash + Eq, V: Clone>(cache: &RwLock<HashMap<K, V>>, key: K, compile: impl FnOnce() -> Result<V, String>) -> Result<V, String> {
+    if let Some(value) = cache.read().ok().and_then(|cache| cache.get(&key).cloned()) {
+        return Ok(value);
+    }
+
+    let value = compile()?;
+
+    if let Ok(mut cache) = cache.write() {
+        if cache.len() < CACHE_CAPACITY {
+            cache.insert(key, value.clone());
+        }
+    }
+
+    Ok(value)
+}
+
+fn render_field(field: Field, datetime: &DateTime<FixedOffset>, locale: Option<&str>) -> String {
+    match field {
+        Field::DayOrdinal => ordinal(datetime.day(), locale),
+        Field::MonthOrdinal => ordinal(datetime.month(), locale),
+        Field::Quarter => (datetime.month0() / 3 + 1).to_string(),
+        Field::QuarterOrdinal => ordinal(datetime.month0() / 3 + 1, locale),
+        Field::QuarterText => format!("{} quarter", ordinal(datetime.month0() / 3 + 1, None)),
+        Field::DayOfYearOrdinal => ordinal(datetime.ordinal(), locale),
+        Field::DaySuffix => ordinal_suffix(datetime.day(), locale).to_string(),
+        Field::DayOfYear0 => datetime.ordinal0().to_string(),
+        Field::DaysInMonth => days_in_month(datetime.year(), datetime.month()).to_string(),
+        Field::LeapYear => u8::from(NaiveDate::from_ymd_opt(datetime.year(), 2, 29).is_some()).to_string(),
+        Field::SwatchBeat => {
+            // Biel Mean Time is UTC+1
+            let seconds = (datetime.to_utc().num_seconds_from_midnight() + 3600) % 86400;
+            format!("{:03}", seconds * 10 / 864)
+        }
+        Field::OffsetSeconds => datetime.offset().local_minus_utc().to_string(),
+        Field::OffsetOrZ(width) => {
+            let offset = datetime.offset().local_minus_utc();
+            let sign = if offset < 0 { '-' } else { '+' };
+            let (hours, minutes) = (offset.unsigned_abs() / 3600, offset.unsigned_abs() % 3600 / 60);
+
+            match (offset, width, minutes) {
+                (0, _, _) => "Z".to_string(),
+                (_, 1, 0) => format!("{}{:02}", sign, hours),
+                (_, 1 | 2, _) => format!("{}{:02}{:02}", sign, hours, minutes),
+                _ => format!("{}{:02}:{:02}", sign, hours, minutes),
+            }
+        }
+        Field::WeekdayMin => datetime.weekday().to_string()[..2].to_string(),
+        Field::ExpandedYear { signed } => {
+            let year = datetime.year();
+            let sign = match year {
+                ..0 => "-",
+                0..=9999 if !signed => "",
+                _ => "+",
+            };
+            format!("{}{:04}", sign, year.unsigned_abs())
+        }
+        Field::Hour24From1 { padded } => padded_to(
+            match datetime.hour() {
+                0 => 24,
+                hour => hour,
+            },
+            padded,
+        ),
+        Field::Hour12From0 { padded } => padded_to(datetime.hour() % 12, padded),
+        Field::Fraction(digits) => format!("{:09}", datetime.nanosecond() % 1_000_000_000)[..usize::from(digits.min(9))].to_string(),
+        Field::Milliseconds => (datetime.nanosecond() % 1_000_000_000 / 1_000_000).to_string(),
+        Field::Nanoseconds => (datetime.nanosecond() % 1_000_000_000).to_string(),
+        Field::TimestampMillis => datetime.timestamp_millis().to_string(),
+    }
+}
+
+/// Number padded to 2 digits, or as is
+fn padded_to(n: u32, padded: bool) -> String {
+    if padded {
+        format!("{:02}", n)
+    } else {
+        n.to_string()
+    }
+}
+
+/// Number of days in the given month
+pub(crate) fn days_in_month(year: i32, month: u32) -> u32 {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .and_then(|next| next.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(31)
+}
+
+/// Ordinal in the language of the locale, ex. `1st` in English, `1er` in French or `1.` in German
+pub(crate) fn ordinal(n: u32, locale: Option<&str>) -> String {
+    format!("{}{}", n, ordinal_suffix(n, locale))
+}
+
+/// Ordinal suffix in the language of the locale, English by default and empty for languages without one
+fn ordinal_suffix(n: u32, locale: Option<&str>) -> &'static str {
+    let language = styles::language(locale);
+
+    match language {
+        "en" => match (n % 10, n % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th",
+        },
+        "fr" => match n {
+            1 => "er",
+            _ => "e",
+        },
+        "nl" => "e",
+        "sv" => match (n % 10, n % 100) {
+            (_, 11 | 12) => ":e",
+            (1 | 2, _) => ":a",
+            _ => ":e",
+        },
+        "es" | "it" | "pt" | "gl" => "º",
+        "de" | "da" | "nb" | "nn" | "no" | "fi" | "pl" | "cs" | "sk" | "hu" | "tr" | "hr" | "sl" | "sr" | "et" | "lv" => ".",
+        _ => "",
+    }
+}
+
+/// Splits a format into runs of the same ASCII letter or quote, every other character is a run of its own
+fn runs(format: &str) -> Vec<(char, usize)> {
+    let mut runs: Vec<(char, usize)> = Vec::new();
+
+    for c in format.chars() {
+        match runs.last_mut() {
+            Some((last, count)) if *last == c && (c.is_ascii_alphabetic() || c == '\'') => *count += 1,
+            _ => runs.push((c, 1)),
+        }
+    }
+
+    runs
+}
+
+fn fraction(compiled: &mut Translation, digits: usize) {
+    match digits {
+        3 => compiled.push_strftime("%3f"),
+        6 => compiled.push_strftime("%6f"),
+        9 => compiled.push_strftime("%9f"),
+        digits => compiled.push_field(Field::Fraction(digits.min(9) as u8)),
+    }
+}
+
+/// Translates Moment.js tokens, see <https://momentjs.com/docs/#/displaying/format/>
+///
+/// The localized formats, as in `LL` or `LT`, take the date and time styles of the locale.
+fn translate_moment(format: &str, locale: &str) -> Result<Translation, String> {
+    let mut compiled = Translation::default();
+    let mut rest = format;
+
+    while !rest.is_empty() {
+        // [escaped text]
+        if let Some(escaped) = rest.strip_prefix('[') {
+            let end = escaped
+                .find(']')
+                .ok_or_else(|| format!("Unterminated `[` in moment format: {}", format))?;
+            compiled.push_literal(&escaped[..end]);
+            rest = &escaped[end + 1..];
+            continue;
+        }
+
+        let token = [
+            "LLLL",
+            "LLL",
+            "LL",
+            "LTS",
+            "LT",
+            "L",
+            "llll",
+            "lll",
+            "ll",
+            "l",
+            "YYYY",
+            "YY",
+            "Y",
+            "Qo",
+            "Q",
+            "MMMM",
+            "MMM",
+            "MM",
+            "Mo",
+            "M",
+            "DDDD",
+            "DDDo",
+            "DDD",
+            "Do",
+            "DD",
+            "D",
+            "dddd",
+            "ddd",
+            "dd",
+            "d",
+            "E",
+            "WW",
+            "W",
+            "ww",
+            "w",
+            "GGGG",
+            "gggg",
+            "HH",
+            "H",
+            "hh",
+            "h",
+            "kk",
+            "k",
+            "mm",
+            "m",
+            "ss",
+            "s",
+            "SSSSSSSSS",
+            "SSSSSSSS",
+            "SSSSSSS",
+            "SSSSSS",
+            "SSSSS",
+            "SSSS",
+            "SSS",
+            "SS",
+            "S",
+            "a",
+            "A",
+            "ZZ",
+            "Z",
+            "zz",
+            "z",
+            "X",
+            "x",
+        ]
+        .into_iter()
+        .filter(|token| rest.starts_with(token))
+        .max_by_key(|token| token.len());
+
+        let Some(token) = token else {
+            let c = rest.chars().next().unwrap_or_default();
+            compiled.push_literal(&rest[..c.len_utf8()]);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        rest = &rest[token.len()..];
+
+        let style = |date, time| styles::pattern(locale, date, time);
+
+        match token {
+            "LT" => compiled.push_strftime(&style(None, Some(Style::Short))),
+            "LTS" => compiled.push_strftime(&style(None, Some(Style::Medium))),
+            "L" | "l" => compiled.push_strftime(&style(Some(Style::Short), None)),
+            "LL" => compiled.push_strftime(&style(Some(Style::Long), None)),
+            "ll" => compiled.push_strftime(&style(Some(Style::Medium), None)),
+            "LLL" => compiled.push_strftime(&style(Some(Style::Long), Some(Style::Short))),
+            "lll" | "llll" => compiled.push_strftime(&style(Some(Style::Medium), Some(Style::Short))),
+            "LLLL" => compiled.push_strftime(&style(Some(Style::Full), Some(Style::Short))),
+            "YYYY" | "Y" => compiled.push_strftime("%Y"),
+            "YY" => compiled.push_strftime("%y"),
+            "Q" => compiled.push_strftime("%q"),
+            "Qo" => compiled.push_field(Field::QuarterOrdinal),
+            "MMMM" => compiled.push_strftime("%B"),
+            "MMM" => compiled.push_strftime("%b"),
+            "MM" => compiled.push_strftime("%m"),
+            "Mo" => compiled.push_field(Field::MonthOrdinal),
+            "M" => compiled.push_strftime("%-m"),
+            "DDDD" => compiled.push_strftime("%j"),
+            "DDDo" => compiled.push_field(Field::DayOfYearOrdinal),
+            "DDD" => compiled.push_strftime("%-j"),
+            "Do" => compiled.push_field(Field::DayOrdinal),
+            "DD" => compiled.push_strftime("%d"),
+            "D" => compiled.push_strftime("%-d"),
+            "dddd" => compiled.push_strftime("%A"),
+            "ddd" => compiled.push_strftime("%a"),
+            "dd" => compiled.push_field(Field::WeekdayMin),
+            "d" => compiled.push_strftime("%w"),
+            "E" => compiled.push_strftime("%u"),
+            "WW" | "ww" => compiled.push_strftime("%V"),
+            "W" | "w" => compiled.push_strftime("%-V"),
+            "GGGG" | "gggg" => compiled.push_strftime("%G"),
+            "HH" => compiled.push_strftime("%H"),
+            "H" => compiled.push_strftime("%-H"),
+            "hh" => compiled.push_strftime("%I"),
+            "h" => compiled.push_strftime("%-I"),
+            "kk" => compiled.push_field(Field::Hour24From1 { padded: true }),
+            "k" => compiled.push_field(Field::Hour24From1 { padded: false }),
+            "mm" => compiled.push_strftime("%M"),
+            "m" => compiled.push_strftime("%-M"),
+            "ss" => compiled.push_strftime("%S"),
+            "s" => compiled.push_strftime("%-S"),
+            "a" => compiled.push_strftime("%P"),
+            "A" => compiled.push_strftime("%p"),
+            "ZZ" => compiled.push_strftime("%z"),
+            "Z" => compiled.push_strftime("%:z"),
+            "zz" | "z" => compiled.push_strftime("%Z"),
+            "X" => compiled.push_strftime("%s"),
+            "x" => compiled.push_field(Field::TimestampMillis),
+            fraction_token => fraction(&mut compiled, fraction_token.len()),
+        }
+    }
+
+    Ok(compiled)
+}
+
+/// Translates Luxon tokens, see <https://moment.github.io/luxon/#/formatting?id=table-of-tokens>
+///
+/// The localized formats, as in `DDD` or `t`, take the date and time styles of the locale.
+fn translate_luxon(format: &str, locale: &str) -> Result<Translation, String> {
+    let mut compiled = Translation::default();
+    let mut quoted = false;
+
+    for (c, count) in runs(format) {
+        if c == '\'' {
+            // '' inside or outside quotes is a single quote, a lone ' toggles quoting
+            compiled.push_literal(&"'".repeat(count / 2));
+            if count % 2 == 1 {
+                quoted = !quoted;
+            }
+            continue;
+        }
+
+        if quoted || !c.is_ascii_alphabetic() {
+            compiled.push_literal(&c.to_string().repeat(count));
+            continue;
+        }
+
+        let style = |count| match count {
+            1 => Style::Short,
+            2 => Style::Medium,
+            3 => Style::Long,
+            _ => Style::Full,
+        };
+
+        match (c, count) {
+            ('D', count) => compiled.push_strftime(&styles::pattern(locale, Some(style(count)), None)),
+            ('t', count) => compiled.push_strftime(&styles::pattern(locale, None, Some(style(count)))),
+            ('T', 1) => compiled.push_strftime("%H:%M"),
+            ('T', 2) => compiled.push_strftime("%H:%M:%S"),
+            ('T', _) => compiled.push_strftime("%H:%M:%S %Z"),
+            // the short and medium forms keep a short (f) or medium (F) time, the long ones follow the date
+            ('f', 1 | 2) => compiled.push_strftime(&styles::pattern(locale, Some(style(count)), Some(Style::Short))),
+            ('F', 1 | 2) => compiled.push_strftime(&styles::pattern(locale, Some(style(count)), Some(Style::Medium))),
+            ('f' | 'F', count) => compiled.push_strftime(&styles::pattern(locale, Some(style(count)), Some(style(count)))),
+            ('y', 2) => compiled.push_strftime("%y"),
+            ('y', _) => compiled.push_strftime("%Y"),
+            ('k', 2) => compiled.push_strftime("%g"),
+            ('k', _) => compiled.push_strftime("%G"),
+            ('q', 1) => compiled.push_strftime("%q"),
+            ('q', _) => compiled.push_strftime("0%q"),
+            ('M' | 'L', 1) => compiled.push_strftime("%-m"),
+            ('M' | 'L', 2) => compiled.push_strftime("%m"),
+            ('M' | 'L', 3) => compiled.push_strftime("%b"),
+            ('M' | 'L', _) => compiled.push_strftime("%B"),
+            ('d', 1) => compiled.push_strftime("%-d"),
+            ('d', _) => compiled.push_strftime("%d"),
+            ('o', 1) => compiled.push_strftime("%-j"),
+            ('o', _) => compiled.push_strftime("%j"),
+            ('E' | 'c', 1) => compiled.push_strftime("%u"),
+            ('E' | 'c', 3) => compiled.push_strftime("%a"),
+            ('E' | 'c', _) => compiled.push_strftime("%A"),
+            ('W', 1) => compiled.push_strftime("%-V"),
+            ('W', _) => compiled.push_strftime("%V"),
+            ('H', 1) => compiled.push_strftime("%-H"),
+            ('H', _) => compiled.push_strftime("%H"),
+            ('h', 1) => compiled.push_strftime("%-I"),
+            ('h', _) => compiled.push_strftime("%I"),
+            ('m', 1) => compiled.push_strftime("%-M"),
+            ('m', _) => compiled.push_strftime("%M"),
+            ('s', 1) => compiled.push_strftime("%-S"),
+            ('s', _) => compiled.push_strftime("%S"),
+            ('S', 1) => compiled.push_field(Field::Milliseconds),
+            ('S', 3) | ('u', 1) => compiled.push_strftime("%3f"),
+            ('u', 2) => compiled.push_field(Field::Fraction(2)),
+            ('u', 3) => compiled.push_field(Field::Fraction(1)),
+            ('a', _) => compiled.push_strftime("%p"),
+            ('Z', 3) => compiled.push_strftime("%z"),
+            ('Z', _) => compiled.push_strftime("%:z"),
+            ('z', _) => compiled.push_strftime("%Z"),
+            ('X', _) => compiled.push_strftime("%s"),
+            ('x', _) => compiled.push_field(Field::TimestampMillis),
+            (c, count) => compiled.push_literal(&c.to_string().repeat(count)),
+        }
+    }
+
+    if quoted {
+        return Err(format!("Unterminated quote in Luxon format: {}", format));
+    }
+
+    Ok(compiled)
+}
+
+/// Translates Java `DateTimeFormatter` patterns, see
+/// <https://docs.oracle.com/javase/8/docs/api/java/time/format/DateTimeFormatter.html>
+fn translate_java(format: &str) -> Result<Translation, String> {
+    let mut compiled = Translation::default();
+    let mut quoted = false;
+
+    for (c, count) in runs(format) {
+        if c == '\'' {
+            compiled.push_literal(&"'".repeat(count / 2));
+            if count % 2 == 1 {
+                quoted = !quoted;
+            }
+            continue;
+        }
+
+        // optional section brackets are ignored, the sections always get printed
+        if !quoted && (c == '[' || c == ']') {
+            continue;
+        }
+
+        if quoted || !c.is_ascii_alphabetic() {
+            compiled.push_literal(&c.to_string().repeat(count));
+            continue;
+        }
+
+        match (c, count) {
+            ('y' | 'u', 2) => compiled.push_strftime("%y"),
+            ('y' | 'u', _) => compiled.push_strftime("%Y"),
+            ('Y', 2) => compiled.push_strftime("%g"),
+            ('Y', _) => compiled.push_strftime("%G"),
+            ('Q' | 'q', 1) => compiled.push_strftime("%q"),
+            ('Q' | 'q', 2) => compiled.push_strftime("0%q"),
+            ('Q' | 'q', 3) => compiled.push_strftime("Q%q"),
+            ('Q' | 'q', _) => compiled.push_field(Field::QuarterText),
+            ('M' | 'L', 1) => compiled.push_strftime("%-m"),
+            ('M' | 'L', 2) => compiled.push_strftime("%m"),
+            ('M' | 'L', 3) => compiled.push_strftime("%b"),
+            ('M' | 'L', _) => compiled.push_strftime("%B"),
+            ('d', 1) => compiled.push_strftime("%-d"),
+            ('d', _) => compiled.push_strftime("%d"),
+            ('D', 1 | 2) => compiled.push_strftime("%-j"),
+            ('D', _) => compiled.push_strftime("%j"),
+            ('E', 1..=3) => compiled.push_strftime("%a"),
+            ('E', _) => compiled.push_strftime("%A"),
+            ('e' | 'c', 1 | 2) => compiled.push_strftime("%u"),
+            ('e' | 'c', 3) => compiled.push_strftime("%a"),
+            ('e' | 'c', _) => compiled.push_strftime("%A"),
+            ('w', 1) => compiled.push_strftime("%-V"),
+            ('w', _) => compiled.push_strftime("%V"),
+            ('a', _) => compiled.push_strftime("%p"),
+            ('H', 1) => compiled.push_strftime("%-H"),
+            ('H', _) => compiled.push_strftime("%H"),
+            ('h', 1) => compiled.push_strftime("%-I"),
+            ('h', _) => compiled.push_strftime("%I"),
+            ('k', count) => compiled.push_field(Field::Hour24From1 { padded: count > 1 }),
+            ('K', count) => compiled.push_field(Field::Hour12From0 { padded: count > 1 }),
+            ('m', 1) => compiled.push_strftime("%-M"),
+            ('m', _) => compiled.push_strftime("%M"),
+            ('s', 1) => compiled.push_strftime("%-S"),
+            ('s', _) => compiled.push_strftime("%S"),
+            ('S', count) => fraction(&mut compiled, count),
+            ('n', _) => compiled.push_field(Field::Nanoseconds),
+            ('Z', 1..=3) | ('x', 2) => compiled.push_strftime("%z"),
+            ('Z', _) | ('x', 3) => compiled.push_strftime("%:z"),
+            ('x', _) => compiled.push_strftime("%z"),
+            ('X', 1) => compiled.push_field(Field::OffsetOrZ(1)),
+            ('X', 2 | 4) => compiled.push_field(Field::OffsetOrZ(2)),
+            ('X', _) => compiled.push_field(Field::OffsetOrZ(3)),
+            ('O', _) | ('V', _) | ('z', _) => compiled.push_strftime("%Z"),
+            (c, _) => return Err(format!("Unsupported Java pattern letter: {}", c)),
+        }
+    }
+
+    if quoted {
+        return Err(format!("Unterminated quote in Java pattern: {}", format));
+    }
+
+    Ok(compiled)
+}
+
+/// Translates PHP `date()` format characters, see <https://www.php.net/manual/en/datetime.format.php>
+fn translate_php(format: &str) -> Result<Translation, String> {
+    let mut compiled = Translation::default();
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    compiled.push_literal(&escaped.to_string());
+                }
+            }
+            // day
+            'd' => compiled.push_strftime("%d"),
+            'D' => compiled.push_strftime("%a"),
+            'j' => compiled.push_strftime("%-d"),
+            'l' => compiled.push_strftime("%A"),
+            'N' => compiled.push_strftime("%u"),
+            'S' => compiled.push_field(Field::DaySuffix),
+            'w' => compiled.push_strftime("%w"),
+            'z' => compiled.push_field(Field::DayOfYear0),
+            // week
+            'W' => compiled.push_strftime("%V"),
+            // month
+            'F' => compiled.push_strftime("%B"),
+            'm' => compiled.push_strftime("%m"),
+            'M' => compiled.push_strftime("%b"),
+            'n' => compiled.push_strftime("%-m"),
+            't' => compiled.push_field(Field::DaysInMonth),
+            // year
+            'L' => compiled.push_field(Field::LeapYear),
+            'o' => compiled.push_strftime("%G"),
+            'X' => compiled.push_field(Field::ExpandedYear { signed: true }),
+            'x' => compiled.push_field(Field::ExpandedYear { signed: false }),
+            'Y' => compiled.push_strftime("%Y"),
+            'y' => compiled.push_strftime("%y"),
+            // time
+            'a' => compiled.push_strftime("%P"),
+            'A' => compiled.push_strftime("%p"),
+            'B' => compiled.push_field(Field::SwatchBeat),
+            'g' => compiled.push_strftime("%-I"),
+            'G' => compiled.push_strftime("%-H"),
+            'h' => compiled.push_strftime("%I"),
+            'H' => compiled.push_strftime("%H"),
+            'i' => compiled.push_strftime("%M"),
+            's' => compiled.push_strftime("%S"),
+            'u' => compiled.push_strftime("%6f"),
+            'v' => compiled.push_strftime("%3f"),
+            // timezone, fixed offsets are never in daylight saving time
+            'e' | 'T' => compiled.push_strftime("%Z"),
+            'I' => compiled.push_literal("0"),
+            'O' => compiled.push_strftime("%z"),
+            'P' => compiled.push_strftime("%:z"),
+            'p' => compiled.push_field(Field::OffsetOrZ(3)),
+            'Z' => compiled.push_field(Field::OffsetSeconds),
+            // full date/time
+            'c' => compiled.push_strftime("%Y-%m-%dT%H:%M:%S%:z"),
+            'r' => compiled.push_strftime("%a, %d %b %Y %H:%M:%S %z"),
+            'U' => compiled.push_strftime("%s"),
+            c => compiled.push_literal(&c.to_string()),
+        }
+    }
+
+    Ok(compiled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: &str, syntax: FormatSyntax) -> String {
+        render_at(format, syntax, "1989-08-09T21:05:01.123456789+02:00")
+    }
+
+    fn render_at(format: &str, syntax: FormatSyntax, datetime: &str) -> String {
+        let datetime = DateTime::parse_from_rfc3339(datetime).unwrap();
+
+        CompiledFormat::compile(format, syntax, None)
+            .expect("Compile error")
+            .render(&datetime)
+            .expect("Format error")
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(render("%Y-%m-%d", FormatSyntax::Strftime), "1989-08-09");
+
+        assert_eq!(render("%B %@o, %Y", FormatSyntax::Strftime), "August 9th, 1989");
+        assert_eq!(render("%-d%@S|Q%@Q|%@q|%@t|%@L", FormatSyntax::Strftime), "9th|Q3|3rd|31|0");
+        assert_eq!(render("100%%@o %G-W%V", FormatSyntax::Strftime), "100%@o 1989-W32");
+
+        assert_eq!(render("MMM Do, YYYY", FormatSyntax::Moment), "Aug 9th, 1989");
+        assert_eq!(
+            render("dddd [the] Do [of] MMMM, h:mm A", FormatSyntax::Moment),
+            "Wednesday the 9th of August, 9:05 PM"
+        );
+        assert_eq!(
+            render("YYYY-MM-DDTHH:mm:ss.SSSZ", FormatSyntax::Moment),
+            "1989-08-09T21:05:01.123+02:00"
+        );
+        assert_eq!(render("Qo [quarter] 100%", FormatSyntax::Moment), "3rd quarter 100%");
+        assert_eq!(render("x", FormatSyntax::Moment), "618692701123");
+        assert_eq!(render("dd kk", FormatSyntax::Moment), "We 21");
+        assert_eq!(render_at("k kk", FormatSyntax::Moment, "1989-08-09T09:05:01+02:00"), "9 09");
+        assert_eq!(
+            render("LT|LTS|L|LL|LLLL", FormatSyntax::Moment),
+            "9:05 PM|9:05:01 PM|8/9/89|August 9, 1989|Wednesday, August 9, 1989, 9:05 PM"
+        );
+        assert_eq!(render("ss.SSSS|S|SS|SSSSSSS", FormatSyntax::Moment), "01.1234|1|12|1234567");
+
+        assert_eq!(render("LLL d, yyyy", FormatSyntax::Luxon), "Aug 9, 1989");
+        assert_eq!(
+            render("yyyy-MM-dd'T'HH:mm:ss.SSSZZ", FormatSyntax::Luxon),
+            "1989-08-09T21:05:01.123+02:00"
+        );
+        assert_eq!(render("EEEE h:mm a 'o''clock'", FormatSyntax::Luxon), "Wednesday 9:05 PM o'clock");
+        assert_eq!(render("s.S|SSS|u|uu|uuu", FormatSyntax::Luxon), "1.123|123|123|12|1");
+        assert_eq!(
+            render("D|DD|DDD|t|T|f", FormatSyntax::Luxon),
+            "8/9/89|Aug 9, 1989|August 9, 1989|9:05 PM|21:05|8/9/89, 9:05 PM"
+        );
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            CompiledFormat::compile("LL", FormatSyntax::Moment, Some("de_DE"))
+                .expect("Compile error")
+                .render(&DateTime::parse_from_rfc3339("1989-08-09T21:05:01+02:00").unwrap())
+                .expect("Format error"),
+            "9. August 1989"
+        );
+        assert_eq!(
+            CompiledFormat::compile("S|SSS", FormatSyntax::Luxon, None)
+                .expect("Compile error")
+                .render(&DateTime::parse_from_rfc3339("1989-08-09T21:05:01.054+02:00").unwrap())
+                .expect("Format error"),
+            "54|054"
+        );
+
+        assert_eq!(render("MMM d, yyyy", FormatSyntax::Java), "Aug 9, 1989");
+        assert_eq!(
+            render("EEE, dd MMM yyyy HH:mm:ss Z", FormatSyntax::Java),
+            "Wed, 09 Aug 1989 21:05:01 +0200"
+        );
+        assert_eq!(
+            render("yyyy-MM-dd'T'HH:mm:ss.SS[XXX]", FormatSyntax::Java),
+            "1989-08-09T21:05:01.12+02:00"
+        );
+        assert_eq!(render("k K 'it''s' QQQQ", FormatSyntax::Java), "21 9 it's 3rd quarter");
+        assert_eq!(render_at("k kk K KK", FormatSyntax::Java, "1989-08-09T09:05:01+02:00"), "9 09 9 09");
+        assert_eq!(render("X|XX|XXX", FormatSyntax::Java), "+02|+0200|+02:00");
+        assert_eq!(
+            render_at("X|XX|XXX", FormatSyntax::Java, "1989-08-09T09:05:01+05:30"),
+            "+0530|+0530|+05:30"
+        );
+        assert_eq!(render_at("X|XX|XXX", FormatSyntax::Java, "1989-08-09T09:05:01Z"), "Z|Z|Z");
+
+        assert_eq!(render("D, d M Y H:i:s", FormatSyntax::Php), "Wed, 09 Aug 1989 21:05:01");
+        assert_eq!(render("jS F Y", FormatSyntax::Php), "9th August 1989");
+        assert_eq!(render("l \\t\\h\\e jS", FormatSyntax::Php), "Wednesday the 9th");
+        assert_eq!(render("t L z N w W o", FormatSyntax::Php), "31 0 220 3 3 32 1989");
+        assert_eq!(render("g:i a|G|h A|u|v", FormatSyntax::Php), "9:05 pm|21|09 PM|123456|123");
+        assert_eq!(render("O P p Z I B", FormatSyntax::Php), "+0200 +02:00 +02:00 7200 0 836");
+        assert_eq!(
+            render("c|r|U", FormatSyntax::Php),
+            "1989-08-09T21:05:01+02:00|Wed, 09 Aug 1989 21:05:01 +0200|618692701"
+        );
+        assert_eq!(render("100% \\Y", FormatSyntax::Php), "100% Y");
+        assert_eq!(render("X|x|Y", FormatSyntax::Php), "+1989|1989|1989");
+        let years = CompiledFormat::compile("X|x", FormatSyntax::Php, None).expect("Compile error");
+        for (year, expected) in [(787, "+0787|0787"), (10191, "+10191|+10191"), (-55, "-0055|-0055")] {
+            let datetime = NaiveDate::from_ymd_opt(year, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+                .fixed_offset();
+            assert_eq!(years.render(&datetime).expect("Format error"), expected);
+        }
+        assert_eq!(
+            CompiledFormat::compile("l jS F, D M", FormatSyntax::Php, Some("fr_FR"))
+                .expect("Compile error")
+                .render(&DateTime::parse_from_rfc3339("1989-08-09T21:05:01+02:00").unwrap())
+                .expect("Format error"),
+            "Wednesday 9th August, Wed Aug"
+        );
+
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(2024, 12), 31);
+
+        assert_eq!(ordinal(1, None), "1st");
+        assert_eq!(ordinal(2, None), "2nd");
+        assert_eq!(ordinal(3, None), "3rd");
+        assert_eq!(ordinal(4, None), "4th");
+        assert_eq!(ordinal(11, None), "11th");
+        assert_eq!(ordinal(12, None), "12th");
+        assert_eq!(ordinal(13, None), "13th");
+        assert_eq!(ordinal(21, None), "21st");
+        assert_eq!(ordinal(112, None), "112th");
+
+        assert_eq!(ordinal(1, Some("fr_FR")), "1er");
+        assert_eq!(ordinal(2, Some("fr_FR")), "2e");
+        assert_eq!(ordinal(16, Some("de_DE")), "16.");
+        assert_eq!(ordinal(16, Some("es_ES")), "16º");
+        assert_eq!(ordinal(21, Some("sv_SE")), "21:a");
+        assert_eq!(ordinal(11, Some("sv_SE")), "11:e");
+        assert_eq!(ordinal(16, Some("ja_JP")), "16");
+
+        assert_eq!("Moment".parse::<FormatSyntax>(), Ok(FormatSyntax::Moment));
+
+        let cache = FormatCache::default();
+        let compiled = cache.output("%Y %@o", FormatSyntax::Strftime, None).expect("Compile error");
+        assert!(Arc::ptr_eq(
+            &compiled,
+            &cache.output("%Y %@o", FormatSyntax::Strftime, None).expect("Compile error")
+        ));
+        assert!(!Arc::ptr_eq(
+            &compiled,
+            &cache
+                .output("%Y %@o", FormatSyntax::Strftime, Some("fr_FR"))
+                .expect("Compile error")
+        ));
+        assert!(cache.input("%Y-%m-%d").is_ok());
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!(CompiledFormat::compile("%Y-%!", FormatSyntax::Strftime, None).is_err());
+        assert!(CompiledFormat::compile("%@x", FormatSyntax::Strftime, None).is_err());
+        assert!(CompiledFormat::compile("[unterminated", FormatSyntax::Moment, None).is_err());
+        assert!(CompiledFormat::compile("'unterminated", FormatSyntax::Luxon, None).is_err());
+        assert!(CompiledFormat::compile("'unterminated", FormatSyntax::Java, None).is_err());
+        assert!(CompiledFormat::compile("G yyyy", FormatSyntax::Java, None).is_err());
+        assert!("cobol".parse::<FormatSyntax>().is_err());
+    }
+}
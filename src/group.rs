@@ -0,0 +1,319 @@
+use chrono::{Datelike, NaiveDate};
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, JsonValue, Output, RenderContext};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::blocks::render_or_recover;
+use crate::calendar::midnight;
+use crate::collections::{dated, Dated};
+use crate::companions::instant;
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::styles;
+use crate::weekday::previous_or_same;
+
+/// Periods the `group_by_date` helper groups items by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Period {
+    #[default]
+    Day,
+    Week,
+    Month,
+    Year,
+    /// Later, tomorrow, today, yesterday and earlier, as in activity feeds
+    Relative,
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            "year" => Ok(Self::Year),
+            "relative" => Ok(Self::Relative),
+            _ => Err(format!(
+                "Unknown by parameter: {}. Supported values are day, week, month, year and relative",
+                s
+            )),
+        }
+    }
+}
+
+impl Period {
+    /// strftime pattern of the label of a group, applied to the start of the period
+    fn label_format(self) -> &'static str {
+        match self {
+            Self::Day | Self::Week => "%-d %B %Y",
+            Self::Month => "%B %Y",
+            Self::Year | Self::Relative => "%Y",
+        }
+    }
+}
+
+/// Groups of `by="relative"`, in chronological order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Bucket {
+    Earlier,
+    Yesterday,
+    Today,
+    Tomorrow,
+    Later,
+}
+
+impl Bucket {
+    fn of(date: NaiveDate, today: NaiveDate) -> Self {
+        match (date - today).num_days() {
+            ..-1 => Self::Earlier,
+            -1 => Self::Yesterday,
+            0 => Self::Today,
+            1 => Self::Tomorrow,
+            _ => Self::Later,
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Self::Earlier => "earlier",
+            Self::Yesterday => "yesterday",
+            Self::Today => "today",
+            Self::Tomorrow => "tomorrow",
+            Self::Later => "later",
+        }
+    }
+
+    /// Label of the group in the language of the locale, English by default
+    fn label(self, locale: Option<&str>) -> &'static str {
+        let language = styles::language(locale);
+
+        match (language, self) {
+            ("de", Self::Earlier) => "Früher",
+            ("de", Self::Yesterday) => "Gestern",
+            ("de", Self::Today) => "Heute",
+            ("de", Self::Tomorrow) => "Morgen",
+            ("de", Self::Later) => "Später",
+            ("fr", Self::Earlier) => "Plus tôt",
+            ("fr", Self::Yesterday) => "Hier",
+            ("fr", Self::Today) => "Aujourd'hui",
+            ("fr", Self::Tomorrow) => "Demain",
+            ("fr", Self::Later) => "Plus tard",
+            ("es", Self::Earlier) => "Anteriormente",
+            ("es", Self::Yesterday) => "Ayer",
+            ("es", Self::Today) => "Hoy",
+            ("es", Self::Tomorrow) => "Mañana",
+            ("es", Self::Later) => "Más adelante",
+            ("it", Self::Earlier) => "Prima",
+            ("it", Self::Yesterday) => "Ieri",
+            ("it", Self::Today) => "Oggi",
+            ("it", Self::Tomorrow) => "Domani",
+            ("it", Self::Later) => "Più avanti",
+            ("pt", Self::Earlier) => "Anteriormente",
+            ("pt", Self::Yesterday) => "Ontem",
+            ("pt", Self::Today) => "Hoje",
+            ("pt", Self::Tomorrow) => "Amanhã",
+            ("pt", Self::Later) => "Mais tarde",
+            ("nl", Self::Earlier) => "Eerder",
+            ("nl", Self::Yesterday) => "Gisteren",
+            ("nl", Self::Today) => "Vandaag",
+            ("nl", Self::Tomorrow) => "Morgen",
+            ("nl", Self::Later) => "Later",
+            (_, Self::Earlier) => "Earlier",
+            (_, Self::Yesterday) => "Yesterday",
+            (_, Self::Today) => "Today",
+            (_, Self::Tomorrow) => "Tomorrow",
+            (_, Self::Later) => "Later",
+        }
+    }
+}
+
+/// `group_by_date` block helper, renders the block once per period of the items, as in `{{#group_by_date posts "published" by="month"}}`
+///
+/// The first parameter is an array, the second one the dotted path of the date time of each item. Each group has
+/// a `label`, a `key`, the `start` of its period, a `count` and its `items`, newest first unless `order="asc"`.
+#[derive(Clone)]
+pub(crate) struct GroupByDate {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl GroupByDate {
+    fn groups(&self, h: &Helper) -> Result<Vec<JsonValue>, String> {
+        let hash = |key| h.hash_get(key).map(|value| value.value().render());
+
+        let by = hash("by").map(|by| by.parse::<Period>()).transpose()?.unwrap_or_default();
+        let descending = match hash("order").as_deref() {
+            Some("asc") => false,
+            Some("desc") | None => true,
+            Some(order) => return Err(format!("Invalid order parameter: {}. Supported values are asc and desc", order)),
+        };
+        let label_format = hash("label_format");
+        let label_format = label_format.as_deref().unwrap_or(by.label_format());
+
+        let locale = hash("locale");
+        let locale = self.helper.locale(locale.as_deref());
+
+        let today = instant(&self.helper, h.hash_get("base").map(|base| base.value()))?.date_naive();
+
+        let mut items = dated(&self.helper, h)?;
+        items.sort_by_key(|(datetime, _)| *datetime);
+        if descending {
+            items.reverse();
+        }
+
+        // Groups by the start of their period, and by bucket for relative groups
+        let mut groups: BTreeMap<(NaiveDate, Option<Bucket>), Vec<Dated>> = BTreeMap::new();
+        for (datetime, item) in items {
+            let date = datetime.date_naive();
+            let key = match by {
+                Period::Day => (date, None),
+                Period::Week => (
+                    previous_or_same(date, self.helper.first_weekday()).ok_or_else(|| "Week out of range".to_string())?,
+                    None,
+                ),
+                Period::Month => (date.with_day(1).ok_or_else(|| "Invalid month".to_string())?, None),
+                Period::Year => (date.with_ordinal(1).ok_or_else(|| "Invalid year".to_string())?, None),
+                Period::Relative => (NaiveDate::MIN, Some(Bucket::of(date, today))),
+            };
+
+            groups.entry(key).or_default().push((datetime, item));
+        }
+
+        let mut groups = groups
+            .into_iter()
+            .map(|((start, bucket), items)| {
+                let (key, label, start) = match bucket {
+                    Some(bucket) => (bucket.key().to_string(), bucket.label(locale).to_string(), JsonValue::Null),
+                    None => {
+                        let offset = items[0].0.timezone();
+                        let start = midnight(offset, start).ok_or_else(|| "Period out of range".to_string())?;
+
+                        (
+                            start.format("%F").to_string(),
+                            self.helper.format(&start, label_format, locale)?,
+                            JsonValue::String(start.to_rfc3339()),
+                        )
+                    }
+                };
+
+                Ok(json!({
+                    "key": key,
+                    "label": label,
+                    "start": start,
+                    "count": items.len(),
+                    "items": items.into_iter().map(|(_, item)| item).collect::<Vec<_>>(),
+                }))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if descending {
+            groups.reverse();
+        }
+
+        Ok(groups)
+    }
+}
+
+impl HelperDef for GroupByDate {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        render_or_recover(&self.helper, self.groups(h), h, r, ctx, rc, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+
+    #[test]
+    fn it_works() {
+        let h = registry();
+        let data = json!({
+            "posts": [
+                {"title": "a", "meta": {"published": "1989-08-09T09:30:11Z"}},
+                {"title": "b", "meta": {"published": 618700000}},
+                {"title": "c", "meta": {"published": "Fri, 01 Sep 1989 10:00:00 +0000"}},
+                {"title": "d", "meta": {"published": "1990-01-02T00:00:00Z"}},
+                {"title": "e", "meta": {"published": "1989-08-14T08:00:00Z"}},
+            ]
+        });
+
+        for (template, comparison) in [
+            (
+                r#"{{#group_by_date posts "meta.published" by="month"}}{{label}} ({{count}}):{{#each items}} {{title}}{{/each}}{{#unless @last}}, {{/unless}}{{/group_by_date}}"#,
+                "January 1990 (1): d, September 1989 (1): c, August 1989 (3): e b a",
+            ),
+            (
+                r#"{{#group_by_date posts "meta.published" by="month" order="asc"}}{{key}}:{{#each items}}{{title}}{{/each}} {{/group_by_date}}"#,
+                "1989-08-01:abe 1989-09-01:c 1990-01-01:d ",
+            ),
+            (
+                r#"{{#group_by_date posts "meta.published" by="day" order="asc"}}{{label}}={{count}} {{/group_by_date}}"#,
+                "9 August 1989=2 14 August 1989=1 1 September 1989=1 2 January 1990=1 ",
+            ),
+            (
+                r#"{{#group_by_date posts "meta.published" by="week" order="asc" label_format="W%V"}}{{label}}/{{start}}={{count}} {{/group_by_date}}"#,
+                "W32/1989-08-07T00:00:00+00:00=2 W33/1989-08-14T00:00:00+00:00=1 W35/1989-08-28T00:00:00+00:00=1 W01/1990-01-01T00:00:00+00:00=1 ",
+            ),
+            (
+                r#"{{#group_by_date posts "meta.published" by="year" as |group|}}{{group.label}}:{{group.count}} {{/group_by_date}}"#,
+                "1990:1 1989:4 ",
+            ),
+            (
+                r#"{{#group_by_date posts "meta.published" by="relative" base="1989-08-14T12:00:00Z"}}{{label}}:{{#each items}}{{title}}{{/each}} {{/group_by_date}}"#,
+                "Later:dc Today:e Earlier:ba ",
+            ),
+            (
+                r#"{{#group_by_date nothing "date"}}{{label}}{{else}}empty{{/group_by_date}}"#,
+                "empty",
+            ),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+
+        #[cfg(feature = "locale")]
+        assert_eq!(
+            h.render_template(
+                r#"{{#group_by_date posts "meta.published" by="relative" base="1989-08-10T12:00:00Z" locale="de_DE" order="asc"}}{{label}} {{/group_by_date}}"#,
+                &data
+            )
+            .expect("Render error"),
+            "Gestern Später ",
+            "Failed to render localized relative groups"
+        );
+    }
+
+    #[test]
+    fn it_craps() {
+        let h = registry();
+        let data = json!({"posts": [{"published": 618658211}, {"title": "undated"}], "dates": [{"published": "yesterday"}]});
+
+        for template in [
+            "{{#group_by_date}}{{/group_by_date}}",
+            r#"{{#group_by_date "posts" "published"}}{{/group_by_date}}"#,
+            r#"{{#group_by_date posts "published"}}{{/group_by_date}}"#,
+            r#"{{#group_by_date dates "published"}}{{/group_by_date}}"#,
+            r#"{{#group_by_date posts.[0] "published" by="decade"}}{{/group_by_date}}"#,
+            r#"{{#group_by_date dates "published" order="random"}}{{/group_by_date}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &data), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+    }
+}
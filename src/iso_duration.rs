@@ -0,0 +1,337 @@
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::calendar::{self, MonthOverflow};
+
+/// ISO 8601 duration, as in `P1Y2M3DT4H5M6.5S`
+///
+/// Calendar parts (years, months, weeks and days) are kept apart from clock parts (hours, minutes and seconds),
+/// so that they can be applied calendar-correctly. Every component carries its own sign, which allows both
+/// `-P1D` and `P1M-2D` forms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct IsoDuration {
+    pub years: i64,
+    pub months: i64,
+    pub weeks: i64,
+    pub days: i64,
+    pub hours: i64,
+    pub minutes: i64,
+    pub seconds: i64,
+    pub nanoseconds: i64,
+}
+
+/// Error produced when parsing an invalid ISO 8601 duration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ParseIsoDurationError(&'static str);
+
+impl Display for ParseIsoDurationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for ParseIsoDurationError {}
+
+impl IsoDuration {
+    /// Total number of months, with years converted to months
+    pub fn total_months(&self) -> Option<i64> {
+        self.years.checked_mul(12)?.checked_add(self.months)
+    }
+
+    /// Total number of days, with weeks converted to days
+    pub fn total_days(&self) -> Option<i64> {
+        self.weeks.checked_mul(7)?.checked_add(self.days)
+    }
+
+    /// Exact clock part of the duration
+    pub fn clock(&self) -> Option<TimeDelta> {
+        TimeDelta::try_hours(self.hours)?
+            .checked_add(&TimeDelta::try_minutes(self.minutes)?)?
+            .checked_add(&TimeDelta::try_seconds(self.seconds)?)?
+            .checked_add(&TimeDelta::nanoseconds(self.nanoseconds))
+    }
+
+    /// Same duration with every component multiplied by `n`
+    pub fn scaled(&self, n: i64) -> Option<Self> {
+        Some(Self {
+            years: self.years.checked_mul(n)?,
+            months: self.months.checked_mul(n)?,
+            weeks: self.weeks.checked_mul(n)?,
+            days: self.days.checked_mul(n)?,
+            hours: self.hours.checked_mul(n)?,
+            minutes: self.minutes.checked_mul(n)?,
+            seconds: self.seconds.checked_mul(n)?,
+            nanoseconds: self.nanoseconds.checked_mul(n)?,
+        })
+    }
+
+    /// Same duration with every component negated, `None` if a component can't be negated
+    pub fn negated(&self) -> Option<Self> {
+        Some(Self {
+            years: self.years.checked_neg()?,
+            months: self.months.checked_neg()?,
+            weeks: self.weeks.checked_neg()?,
+            days: self.days.checked_neg()?,
+            hours: self.hours.checked_neg()?,
+            minutes: self.minutes.checked_neg()?,
+            seconds: self.seconds.checked_neg()?,
+            nanoseconds: self.nanoseconds.checked_neg()?,
+        })
+    }
+
+    /// Applies the duration to a `DateTime`
+    ///
+    /// Months and years are applied first, following the given month overflow policy, then weeks and days,
+    /// which both follow the calendar. The clock part is applied last as an exact amount of time.
+    pub fn add_to(&self, datetime: DateTime<FixedOffset>, overflow: MonthOverflow) -> Option<DateTime<FixedOffset>> {
+        self.add_days_to(self.add_months_to(datetime, overflow)?)
+    }
+
+    /// Applies the years and months of the duration only, following the given month overflow policy
+    pub fn add_months_to(&self, datetime: DateTime<FixedOffset>, overflow: MonthOverflow) -> Option<DateTime<FixedOffset>> {
+        calendar::add_months(datetime, self.total_months()?, overflow)
+    }
+
+    /// Applies everything but the years and months of the duration
+    pub fn add_days_to(&self, datetime: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let datetime = calendar::add_days(datetime, self.total_days()?)?;
+
+        datetime.checked_add_signed(self.clock()?)
+    }
+}
+
+impl FromStr for IsoDuration {
+    type Err = ParseIsoDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (negative, s) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+
+        let s = s
+            .strip_prefix(['P', 'p'])
+            .ok_or(ParseIsoDurationError("ISO 8601 duration must start with `P`"))?;
+
+        let (date_part, time_part) = match s.find(['T', 't']) {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None),
+        };
+
+        let mut duration = IsoDuration::default();
+        let mut components = 0;
+
+        // designators have to appear in this exact order
+        let mut date_designators = ['Y', 'M', 'W', 'D'].iter();
+        for (value, fraction, designator) in components_of(date_part)? {
+            if fraction.is_some() {
+                return Err(ParseIsoDurationError("Only the seconds of an ISO 8601 duration can be fractional"));
+            }
+
+            let field = match date_designators.find(|d| **d == designator) {
+                Some('Y') => &mut duration.years,
+                Some('M') => &mut duration.months,
+                Some('W') => &mut duration.weeks,
+                Some('D') => &mut duration.days,
+                _ => {
+                    return Err(ParseIsoDurationError(
+                        "Unexpected or misplaced date designator in ISO 8601 duration",
+                    ))
+                }
+            };
+
+            *field = value;
+            components += 1;
+        }
+
+        if let Some(time_part) = time_part {
+            if time_part.is_empty() {
+                return Err(ParseIsoDurationError(
+                    "ISO 8601 duration time designator `T` must be followed by a time component",
+                ));
+            }
+
+            let mut time_designators = ['H', 'M', 'S'].iter();
+            for (value, fraction, designator) in components_of(time_part)? {
+                let field = match time_designators.find(|d| **d == designator) {
+                    Some('H') => &mut duration.hours,
+                    Some('M') => &mut duration.minutes,
+                    Some('S') => {
+                        duration.nanoseconds = fraction.unwrap_or_default();
+                        &mut duration.seconds
+                    }
+                    _ => {
+                        return Err(ParseIsoDurationError(
+                            "Unexpected or misplaced time designator in ISO 8601 duration",
+                        ))
+                    }
+                };
+
+                if fraction.is_some() && designator != 'S' {
+                    return Err(ParseIsoDurationError("Only the seconds of an ISO 8601 duration can be fractional"));
+                }
+
+                *field = value;
+                components += 1;
+            }
+        }
+
+        if components == 0 {
+            return Err(ParseIsoDurationError("ISO 8601 duration must contain at least one component"));
+        }
+
+        if negative {
+            duration.negated().ok_or(ParseIsoDurationError("ISO 8601 duration out of range"))
+        } else {
+            Ok(duration)
+        }
+    }
+}
+
+/// Splits a date or time part into `(value, signed fraction in nano-seconds, designator)` triplets
+fn components_of(part: &str) -> Result<Vec<(i64, Option<i64>, char)>, ParseIsoDurationError> {
+    let mut components = Vec::new();
+    let mut rest = part;
+
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| c.is_ascii_alphabetic())
+            .ok_or(ParseIsoDurationError("ISO 8601 duration component is missing its designator"))?;
+
+        let (number, designator) = (&rest[..end], rest[end..].chars().next().unwrap_or_default().to_ascii_uppercase());
+        rest = &rest[end + 1..];
+
+        let (whole, fraction) = match number.find(['.', ',']) {
+            Some(pos) => (&number[..pos], Some(&number[pos + 1..])),
+            None => (number, None),
+        };
+
+        if whole.trim_start_matches(['-', '+']).is_empty() {
+            return Err(ParseIsoDurationError("ISO 8601 duration component is missing its value"));
+        }
+
+        let value = whole
+            .parse::<i64>()
+            .map_err(|_e| ParseIsoDurationError("Invalid ISO 8601 duration component value"))?;
+
+        let fraction = match fraction {
+            Some(digits) if digits.is_empty() || digits.len() > 9 || !digits.chars().all(|c| c.is_ascii_digit()) => {
+                return Err(ParseIsoDurationError("ISO 8601 duration fraction must have between 1 and 9 digits"));
+            }
+            Some(digits) => {
+                let nanos = format!("{:0<9}", digits).parse::<i64>().unwrap_or_default();
+                Some(if whole.starts_with('-') { -nanos } else { nanos })
+            }
+            None => None,
+        };
+
+        components.push((value, fraction, designator));
+    }
+
+    Ok(components)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses() {
+        assert_eq!(
+            "P1Y2M3DT4H5M6S".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                years: 1,
+                months: 2,
+                days: 3,
+                hours: 4,
+                minutes: 5,
+                seconds: 6,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            "P2W".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                weeks: 2,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            "-P1DT12H".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                days: -1,
+                hours: -12,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            "P1M-2D".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                months: 1,
+                days: -2,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            "PT1.5S".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                seconds: 1,
+                nanoseconds: 500_000_000,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            "-PT0,25S".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                seconds: 0,
+                nanoseconds: -250_000_000,
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            "PT-0.25S".parse::<IsoDuration>(),
+            Ok(IsoDuration {
+                seconds: 0,
+                nanoseconds: -250_000_000,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn it_craps() {
+        for invalid in [
+            "",
+            "P",
+            "PT",
+            "1D",
+            "P1",
+            "PD",
+            "P1H",
+            "PT1D",
+            "P1D2Y",
+            "P1.5D",
+            "PT1.5H",
+            "PT1.S",
+            "PT1.0123456789S",
+            "P1X",
+            "-P-9223372036854775808D",
+        ] {
+            assert!(
+                invalid.parse::<IsoDuration>().is_err(),
+                "Failed to produce error with `{}`",
+                invalid
+            );
+        }
+    }
+}
@@ -1,3 +1,45 @@
+mod age;
+mod blocks;
+mod business;
+mod calendar;
+mod calendar_month;
+mod calendar_time;
+mod collections;
+mod companions;
+mod config;
+mod cron;
 mod datetime;
+mod duration;
+mod each_date;
+mod family;
+mod format;
+mod group;
+mod iso_duration;
+mod presets;
+mod range;
+mod rrule;
+mod spec;
+mod styles;
+mod weekday;
 
+pub use age::AgePrecision;
+pub use business::{HolidayCalendar, IcsError, MemoryHolidayCalendar};
+pub use calendar_time::CalendarFormats;
+pub use config::{ConfigError, HelperConfig};
+pub use cron::{Cron, CronError, CronOccurrence};
 pub use datetime::HandlebarsChronoDateTime;
+pub use family::{register, HelperFamily};
+pub use format::{FormatError, FormatSyntax};
+pub use presets::FormatPreset;
+pub use rrule::{Occurrences, RRule, RRuleError};
+pub use spec::{DateTimeSpec, Finalizer, Initializer, SpecError, Timezone};
+pub use styles::Style;
+
+/// Registry with the whole helper family under the default names, shared by the tests of the helpers
+#[cfg(test)]
+pub(crate) fn registry() -> handlebars::Handlebars<'static> {
+    let mut h = handlebars::Handlebars::new();
+    register(&mut h, "");
+
+    h
+}
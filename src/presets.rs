@@ -0,0 +1,90 @@
+/// Named output format, selected with the `format` hash parameter
+///
+/// ```rust
+/// use handlebars::Handlebars;
+/// use handlebars_chrono::{FormatPreset, HandlebarsChronoDateTime};
+/// use serde_json::json;
+///
+/// let mut h = Handlebars::new();
+/// h.register_helper(
+///     "datetime",
+///     Box::new(
+///         HandlebarsChronoDateTime::default()
+///             .with_format("invoice", "%d.%m.%Y")
+///             .with_format_preset("log", FormatPreset::new("%Y-%m-%d %H:%M:%S UTC").in_utc()),
+///     ),
+/// );
+///
+/// assert_eq!(h.render_template(r#"{{datetime from_timestamp="618658211" format="invoice"}}"#, &json!({})).expect("Render error"), "09.08.1989");
+/// assert_eq!(h.render_template(r#"{{datetime from_timestamp="618658211" with_timezone="+02:00" format="log"}}"#, &json!({})).expect("Render error"), "1989-08-09 09:30:11 UTC");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatPreset {
+    format: String,
+    utc: bool,
+    localized: bool,
+}
+
+impl FormatPreset {
+    /// Preset using the given [strftime](https://docs.rs/chrono/latest/chrono/format/strftime/index.html) format
+    pub fn new<S: Into<String>>(format: S) -> Self {
+        Self {
+            format: format.into(),
+            utc: false,
+            localized: true,
+        }
+    }
+
+    /// Converts the `DateTime` to UTC before formatting, as required by formats like HTTP-date
+    pub fn in_utc(mut self) -> Self {
+        self.utc = true;
+        self
+    }
+
+    /// Ignores the `locale`, so day and month names stay in English, as required by wire formats like HTTP-date
+    pub fn unlocalized(mut self) -> Self {
+        self.localized = false;
+        self
+    }
+
+    /// The strftime format of the preset
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    /// Whether the `DateTime` gets converted to UTC before formatting
+    pub fn is_utc(&self) -> bool {
+        self.utc
+    }
+
+    /// Whether the `locale` applies to the preset
+    pub fn is_localized(&self) -> bool {
+        self.localized
+    }
+
+    /// Built-in preset by name, always rendered in English since they are machine-readable formats
+    ///
+    /// - `iso8601`: ISO 8601 extended format, ex. `1989-08-09T07:30:11+00:00`
+    /// - `iso8601_basic`: ISO 8601 basic format, ex. `19890809T073011+0000`
+    /// - `http_date`: HTTP-date as defined by RFC 7231, ex. `Wed, 09 Aug 1989 07:30:11 GMT`
+    /// - `rfc822` or `rss`: RSS `pubDate`, ex. `Wed, 09 Aug 1989 07:30:11 +0000`
+    /// - `atom`: Atom `updated`, ex. `1989-08-09T07:30:11+00:00`
+    /// - `cookie`: Cookie `Expires` attribute, ex. `Wed, 09 Aug 1989 07:30:11 GMT`
+    /// - `sql_datetime`: SQL `DATETIME`, ex. `1989-08-09 07:30:11`
+    /// - `sql_date`: SQL `DATE`, ex. `1989-08-09`
+    /// - `icalendar`: iCalendar UTC `DTSTART`, ex. `19890809T073011Z`
+    pub fn builtin(name: &str) -> Option<Self> {
+        let preset = match name {
+            "iso8601" | "atom" => Self::new("%Y-%m-%dT%H:%M:%S%:z"),
+            "iso8601_basic" => Self::new("%Y%m%dT%H%M%S%z"),
+            "http_date" | "cookie" => Self::new("%a, %d %b %Y %H:%M:%S GMT").in_utc(),
+            "rfc822" | "rss" => Self::new("%a, %d %b %Y %H:%M:%S %z"),
+            "sql_datetime" => Self::new("%Y-%m-%d %H:%M:%S"),
+            "sql_date" => Self::new("%Y-%m-%d"),
+            "icalendar" => Self::new("%Y%m%dT%H%M%SZ").in_utc(),
+            _ => return None,
+        };
+
+        Some(preset.unlocalized())
+    }
+}
@@ -0,0 +1,296 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+use handlebars::{Context, Handlebars, Helper, HelperDef, JsonRender, RenderContext, RenderError, ScopedJson};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::companions::instant;
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::styles;
+
+/// Parts of the date times shown by the `daterange` helper
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Show {
+    #[default]
+    Date,
+    Time,
+    DateTime,
+}
+
+impl FromStr for Show {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "date" => Ok(Self::Date),
+            "time" => Ok(Self::Time),
+            "datetime" => Ok(Self::DateTime),
+            _ => Err(format!(
+                "Unknown show parameter: {}. Supported values are date, time and datetime",
+                s
+            )),
+        }
+    }
+}
+
+/// strftime patterns of date ranges in the conventions of a locale
+struct Patterns {
+    /// First and last date of a range within a month, as in `9` and `11 Aug 1989`
+    same_month: (&'static str, &'static str),
+    /// First and last date of a range within a year, as in `30 Aug` and `2 Sep 1989`
+    same_year: (&'static str, &'static str),
+    /// Any other date, as in `9 Aug 1989`
+    date: &'static str,
+    time: &'static str,
+}
+
+impl Patterns {
+    /// Patterns of the locale, day-month-year with a 24-hour clock by default
+    fn of(locale: Option<&str>) -> Self {
+        match (styles::language(locale), locale) {
+            (_, Some("en_US")) => Self {
+                same_month: ("%b %-d", "%-d, %Y"),
+                same_year: ("%b %-d", "%b %-d, %Y"),
+                date: "%b %-d, %Y",
+                time: "%-I:%M %p",
+            },
+            ("de" | "da" | "nb" | "nn" | "no" | "fi" | "cs" | "sk" | "hr" | "sl" | "et" | "lv", _) => Self {
+                same_month: ("%-d.", "%-d. %b %Y"),
+                same_year: ("%-d. %b", "%-d. %b %Y"),
+                date: "%-d. %b %Y",
+                time: "%H:%M",
+            },
+            _ => Self {
+                same_month: ("%-d", "%-d %b %Y"),
+                same_year: ("%-d %b", "%-d %b %Y"),
+                date: "%-d %b %Y",
+                time: "%H:%M",
+            },
+        }
+    }
+}
+
+/// Joins the two ends of a range, with a spaced dash unless the first end is a single word like `9` or `14:00`
+fn join(first: String, last: String) -> String {
+    if first.contains(' ') {
+        format!("{} – {}", first, last)
+    } else {
+        format!("{}–{}", first, last)
+    }
+}
+
+/// Formats a range of date times, collapsing the parts both ends share, as in `9–11 Aug 1989`
+///
+/// Fails when the range ends before it starts. Times alone are only shown for ranges within a day, longer ones
+/// get the date and time of both ends.
+pub(crate) fn format(
+    helper: &HandlebarsChronoDateTime,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    show: Show,
+    locale: Option<&str>,
+) -> Result<String, String> {
+    if start > end {
+        return Err(format!("Range starts after it ends: {} – {}", start.to_rfc3339(), end.to_rfc3339()));
+    }
+
+    let patterns = Patterns::of(locale);
+    let format = |datetime, pattern| helper.format(datetime, pattern, locale);
+
+    let same_day = start.date_naive() == end.date_naive();
+
+    let times = || -> Result<String, String> {
+        let first = format(&start, patterns.time)?;
+        let last = format(&end, patterns.time)?;
+
+        Ok(if first == last { first } else { join(first, last) })
+    };
+
+    let dates = || -> Result<String, String> {
+        if same_day {
+            format(&start, patterns.date)
+        } else if (start.year(), start.month()) == (end.year(), end.month()) {
+            Ok(join(format(&start, patterns.same_month.0)?, format(&end, patterns.same_month.1)?))
+        } else if start.year() == end.year() {
+            Ok(join(format(&start, patterns.same_year.0)?, format(&end, patterns.same_year.1)?))
+        } else {
+            Ok(join(format(&start, patterns.date)?, format(&end, patterns.date)?))
+        }
+    };
+
+    match show {
+        Show::Date => dates(),
+        Show::Time if same_day => times(),
+        Show::DateTime if same_day => Ok(format!("{}, {}", dates()?, times()?)),
+        Show::Time | Show::DateTime => Ok(format!(
+            "{}, {} – {}, {}",
+            format(&start, patterns.date)?,
+            format(&start, patterns.time)?,
+            format(&end, patterns.date)?,
+            format(&end, patterns.time)?
+        )),
+    }
+}
+
+/// `daterange` helper, as in `{{daterange event.start event.end}}` for `9–11 Aug 1989`
+///
+/// Both parameters are UNIX timestamps in seconds, RFC3339 or RFC2822 date times. The `show` hash parameter picks
+/// `date` (default), `time` or `datetime`, and the conventions follow `locale`.
+#[derive(Clone)]
+pub(crate) struct FormatRange {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl FormatRange {
+    fn render(&self, h: &Helper) -> Result<String, String> {
+        let (start, end) = match (h.param(0), h.param(1)) {
+            (Some(start), Some(end)) => (
+                instant(&self.helper, Some(start.value()))?,
+                instant(&self.helper, Some(end.value()))?,
+            ),
+            _ => return Err("Missing date time parameters of `daterange`, expected the start and the end".to_string()),
+        };
+
+        let show = h
+            .hash_get("show")
+            .map(|show| show.value().render().parse())
+            .transpose()?
+            .unwrap_or_default();
+
+        let locale = h.hash_get("locale").map(|locale| locale.value().render());
+
+        format(&self.helper, start, end, show, self.helper.locale(locale.as_deref()))
+    }
+}
+
+impl HelperDef for FormatRange {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _r: &'reg Handlebars,
+        _ctx: &'rc Context,
+        _rc: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        Ok(self.helper.recover(self.render(h))?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "locale")]
+    use super::*;
+    use crate::registry;
+    use serde_json::json;
+
+    #[test]
+    fn it_works() {
+        let h = registry();
+
+        for (start, end, show, comparison) in [
+            ("1989-08-09T10:00:00Z", "1989-08-11T10:00:00Z", "date", "9–11 Aug 1989"),
+            ("1989-08-30T10:00:00Z", "1989-09-02T10:00:00Z", "date", "30 Aug – 2 Sep 1989"),
+            ("1989-12-30T10:00:00Z", "1990-01-02T10:00:00Z", "date", "30 Dec 1989 – 2 Jan 1990"),
+            ("1989-08-09T10:00:00Z", "1989-08-09T18:00:00Z", "date", "9 Aug 1989"),
+            ("1989-08-09T14:00:00Z", "1989-08-09T15:30:00Z", "time", "14:00–15:30"),
+            (
+                "1989-08-09T14:00:00Z",
+                "1989-08-09T15:30:00Z",
+                "datetime",
+                "9 Aug 1989, 14:00–15:30",
+            ),
+            (
+                "1989-08-09T14:00:00Z",
+                "1989-08-10T09:00:00Z",
+                "datetime",
+                "9 Aug 1989, 14:00 – 10 Aug 1989, 09:00",
+            ),
+            (
+                "1989-08-09T23:00:00Z",
+                "1989-08-10T01:00:00Z",
+                "time",
+                "9 Aug 1989, 23:00 – 10 Aug 1989, 01:00",
+            ),
+        ] {
+            assert_eq!(
+                h.render_template(
+                    r#"{{daterange start end show=show}}"#,
+                    &json!({"start": start, "end": end, "show": show})
+                )
+                .expect("Render error"),
+                comparison,
+                "Failed to render range from {} to {}",
+                start,
+                end
+            );
+        }
+
+        #[cfg(feature = "locale")]
+        {
+            let helper = HandlebarsChronoDateTime::default();
+            let date = |datetime| DateTime::parse_from_rfc3339(datetime).expect("Parse error");
+
+            for (locale, start, end, show, comparison) in [
+                (
+                    "en_US",
+                    "1989-08-09T10:00:00Z",
+                    "1989-08-11T10:00:00Z",
+                    Show::Date,
+                    "Aug 9 – 11, 1989",
+                ),
+                (
+                    "en_US",
+                    "1989-08-30T10:00:00Z",
+                    "1989-09-02T10:00:00Z",
+                    Show::Date,
+                    "Aug 30 – Sep 2, 1989",
+                ),
+                (
+                    "en_US",
+                    "1989-08-09T14:00:00Z",
+                    "1989-08-09T15:30:00Z",
+                    Show::Time,
+                    "2:00 PM – 3:30 PM",
+                ),
+                (
+                    "de_DE",
+                    "1989-08-09T10:00:00Z",
+                    "1989-08-11T10:00:00Z",
+                    Show::Date,
+                    "9.–11. Aug 1989",
+                ),
+                (
+                    "fr_FR",
+                    "1989-08-30T10:00:00Z",
+                    "1989-09-02T10:00:00Z",
+                    Show::Date,
+                    "30 août – 2 sept. 1989",
+                ),
+            ] {
+                assert_eq!(
+                    format(&helper, date(start), date(end), show, Some(locale)).expect("Format error"),
+                    comparison,
+                    "Failed to render range in {}",
+                    locale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_craps() {
+        let h = registry();
+
+        for template in [
+            "{{daterange}}",
+            "{{daterange 618658211}}",
+            r#"{{daterange 618658211 "tomorrow"}}"#,
+            r#"{{daterange 618658211 618658212 show="week"}}"#,
+            "{{daterange 618658212 618658211}}",
+        ] {
+            assert!(
+                matches!(h.render_template(template, &json!({})), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+    }
+}
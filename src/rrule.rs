@@ -0,0 +1,961 @@
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeDelta, Timelike, Utc, Weekday,
+};
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, JsonTruthy, JsonValue, Output, RenderContext};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::blocks::render_or_recover;
+use crate::companions::{instant, parts};
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::spec::Timezone;
+use crate::weekday::previous_or_same;
+
+/// Days without occurrences after which the expansion ends, so a rule that never matches, as in Feb 30, ends
+const MAX_EMPTY_DAYS: i64 = 28 * 366;
+
+/// Periods without occurrences the expansion goes through at least, so rules with a long `INTERVAL` don't end early
+const MIN_EMPTY_PERIODS: u32 = 28;
+
+/// Upper bound of periods scanned, so a rule with `COUNT`, which has to be expanded from `DTSTART`, can't run forever
+const MAX_PERIODS: u32 = 1_000_000;
+
+/// Upper bound of occurrences rendered by `each_occurrence`
+const MAX_OCCURRENCES: usize = 10_000;
+
+/// `FREQ` of a rule, from the longest to the shortest period
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Frequency {
+    Yearly,
+    Monthly,
+    Weekly,
+    Daily,
+    Hourly,
+    Minutely,
+    Secondly,
+}
+
+impl FromStr for Frequency {
+    type Err = RRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "YEARLY" => Ok(Self::Yearly),
+            "MONTHLY" => Ok(Self::Monthly),
+            "WEEKLY" => Ok(Self::Weekly),
+            "DAILY" => Ok(Self::Daily),
+            "HOURLY" => Ok(Self::Hourly),
+            "MINUTELY" => Ok(Self::Minutely),
+            "SECONDLY" => Ok(Self::Secondly),
+            _ => Err(RRuleError(format!("Invalid FREQ: {}", s))),
+        }
+    }
+}
+
+/// `UNTIL` of a rule, a local date time unless given in UTC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Until {
+    Local(NaiveDateTime),
+    Utc(DateTime<Utc>),
+}
+
+/// iCalendar recurrence rule, see RFC 5545 section 3.3.10
+///
+/// Supports every rule part but `BYWEEKNO`. The rule is expanded from a `DTSTART` in the local time of a timezone, so
+/// `FREQ=DAILY` at 09:00 in `Europe/Sofia` stays at 09:00 across DST changes.
+///
+/// ```rust
+/// use chrono::DateTime;
+/// use handlebars_chrono::{RRule, Timezone};
+///
+/// let rule: RRule = "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4".parse().unwrap();
+/// let dtstart = DateTime::parse_from_rfc3339("2024-01-01T18:00:00+02:00").unwrap();
+///
+/// let occurrences = rule
+///     .occurrences(dtstart, Timezone::Fixed(*dtstart.offset()))
+///     .map(|occurrence| occurrence.to_rfc3339())
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     occurrences,
+///     [
+///         "2024-01-01T18:00:00+02:00",
+///         "2024-01-03T18:00:00+02:00",
+///         "2024-01-08T18:00:00+02:00",
+///         "2024-01-10T18:00:00+02:00",
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RRule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<Until>,
+    by_month: Vec<u32>,
+    by_month_day: Vec<i32>,
+    by_year_day: Vec<i32>,
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_hour: Vec<u32>,
+    by_minute: Vec<u32>,
+    by_second: Vec<u32>,
+    by_set_pos: Vec<i32>,
+    week_start: Weekday,
+}
+
+impl FromStr for RRule {
+    type Err = RRuleError;
+
+    /// Parses a rule, as in `FREQ=MONTHLY;BYDAY=-1FR` or `RRULE:FREQ=DAILY;COUNT=5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut frequency = None;
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_month: Vec::new(),
+            by_month_day: Vec::new(),
+            by_year_day: Vec::new(),
+            by_day: Vec::new(),
+            by_hour: Vec::new(),
+            by_minute: Vec::new(),
+            by_second: Vec::new(),
+            by_set_pos: Vec::new(),
+            week_start: Weekday::Mon,
+        };
+
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| RRuleError(format!("Invalid rule part: {}", part)))?;
+            let value = value.to_uppercase();
+
+            match key.to_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.parse()?),
+                "INTERVAL" => rule.interval = number(key, &value, 1..=u32::MAX)?,
+                "COUNT" => rule.count = Some(number(key, &value, 1..=u32::MAX)?),
+                "UNTIL" => rule.until = Some(until(&value)?),
+                "BYMONTH" => rule.by_month = list(key, &value, |month| number(key, month, 1..=12))?,
+                "BYMONTHDAY" => rule.by_month_day = list(key, &value, |day| signed(key, day, 31))?,
+                "BYYEARDAY" => rule.by_year_day = list(key, &value, |day| signed(key, day, 366))?,
+                "BYDAY" => rule.by_day = list(key, &value, nth_weekday)?,
+                "BYHOUR" => rule.by_hour = list(key, &value, |hour| number(key, hour, 0..=23))?,
+                "BYMINUTE" => rule.by_minute = list(key, &value, |minute| number(key, minute, 0..=59))?,
+                "BYSECOND" => rule.by_second = list(key, &value, |second| number(key, second, 0..=59))?,
+                "BYSETPOS" => rule.by_set_pos = list(key, &value, |position| signed(key, position, 366))?,
+                "WKST" => rule.week_start = weekday(&value)?,
+                "BYWEEKNO" => return Err(RRuleError("Unsupported rule part: BYWEEKNO".to_string())),
+                _ => return Err(RRuleError(format!("Unknown rule part: {}", key))),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| RRuleError("Missing FREQ rule part".to_string()))?;
+
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(RRuleError("COUNT and UNTIL can't be used together".to_string()));
+        }
+
+        Ok(rule)
+    }
+}
+
+impl RRule {
+    /// Occurrences of the rule from `dtstart` on, expanded in the local time of `timezone`
+    ///
+    /// The iterator ends with `COUNT` or `UNTIL`, after 28 years without occurrences, or after a million periods.
+    pub fn occurrences(&self, dtstart: DateTime<FixedOffset>, timezone: Timezone) -> Occurrences<'_> {
+        let start = timezone.convert(dtstart.to_utc()).naive_local();
+
+        Occurrences {
+            rule: self,
+            timezone,
+            start,
+            period: 0,
+            last: start,
+            empty_periods: 0,
+            scanned: 0,
+            emitted: 0,
+            pending: VecDeque::new(),
+            done: false,
+            exhausted: false,
+        }
+    }
+
+    /// First occurrence of the rule strictly after `after`
+    pub fn after(&self, dtstart: DateTime<FixedOffset>, timezone: Timezone, after: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        self.occurrences(dtstart, timezone)
+            .skip_to(after)
+            .find(|occurrence| *occurrence > after)
+    }
+
+    /// Whether the rule ends, with either `COUNT` or `UNTIL`
+    pub fn is_finite(&self) -> bool {
+        self.count.is_some() || self.until.is_some()
+    }
+
+    /// Beginning and candidate local date times of the `n`th period from `start`, sorted, `None` when out of range
+    fn period(&self, start: NaiveDateTime, n: i64) -> Option<(NaiveDateTime, Vec<NaiveDateTime>)> {
+        let step = n.checked_mul(i64::from(self.interval))?;
+
+        let (begin, dates, moment) = match self.frequency {
+            Frequency::Yearly => {
+                let year = start.year().checked_add(i32::try_from(step).ok()?)?;
+                // ends the expansion past the supported years
+                NaiveDate::from_yo_opt(year, 1)?;
+
+                // only the days of the BYMONTH months, as the others can't match
+                let months = if self.by_month.is_empty() {
+                    (1..=12).collect()
+                } else {
+                    self.by_month.clone()
+                };
+                let dates = months
+                    .into_iter()
+                    .filter_map(|month| NaiveDate::from_ymd_opt(year, month, 1))
+                    .flat_map(|first| first.iter_days().take_while(move |date| date.month() == first.month()))
+                    .collect();
+
+                (NaiveDate::from_yo_opt(year, 1)?.and_time(NaiveTime::MIN), dates, None)
+            }
+            Frequency::Monthly => {
+                let first = start
+                    .date()
+                    .with_day(1)?
+                    .checked_add_months(Months::new(u32::try_from(step).ok()?))?;
+                (
+                    first.and_time(NaiveTime::MIN),
+                    first.iter_days().take_while(|date| date.month() == first.month()).collect(),
+                    None,
+                )
+            }
+            Frequency::Weekly => {
+                let first = previous_or_same(start.date(), self.week_start)?
+                    .checked_add_days(Days::new(u64::try_from(step).ok()?.checked_mul(7)?))?;
+                (first.and_time(NaiveTime::MIN), first.iter_days().take(7).collect(), None)
+            }
+            Frequency::Daily => {
+                let date = start.date().checked_add_days(Days::new(u64::try_from(step).ok()?))?;
+                (date.and_time(NaiveTime::MIN), vec![date], None)
+            }
+            Frequency::Hourly | Frequency::Minutely | Frequency::Secondly => {
+                let delta = match self.frequency {
+                    Frequency::Hourly => TimeDelta::try_hours(step)?,
+                    Frequency::Minutely => TimeDelta::try_minutes(step)?,
+                    _ => TimeDelta::try_seconds(step)?,
+                };
+                let moment = start.checked_add_signed(delta)?;
+
+                (moment, vec![moment.date()], Some(moment))
+            }
+        };
+
+        // A component of the time is fixed by the period when the frequency is as short, and limited by its BY* part,
+        // otherwise it is expanded to the BY* part, or taken from DTSTART
+        let component = |frequency, by: &Vec<u32>, fixed: Option<u32>, default: u32| match (self.frequency >= frequency, fixed) {
+            (true, Some(value)) if by.is_empty() || by.contains(&value) => vec![value],
+            (true, _) => Vec::new(),
+            (false, _) if by.is_empty() => vec![default],
+            (false, _) => by.clone(),
+        };
+        let hours = component(Frequency::Hourly, &self.by_hour, moment.map(|moment| moment.hour()), start.hour());
+        let minutes = component(
+            Frequency::Minutely,
+            &self.by_minute,
+            moment.map(|moment| moment.minute()),
+            start.minute(),
+        );
+        let seconds = component(
+            Frequency::Secondly,
+            &self.by_second,
+            moment.map(|moment| moment.second()),
+            start.second(),
+        );
+
+        let mut candidates = Vec::new();
+        for date in dates.into_iter().filter(|date| self.matches(*date, start.date())) {
+            for hour in &hours {
+                for minute in &minutes {
+                    for second in &seconds {
+                        candidates.push(date.and_time(NaiveTime::from_hms_opt(*hour, *minute, *second)?));
+                    }
+                }
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+
+        if self.by_set_pos.is_empty() {
+            return Some((begin, candidates));
+        }
+
+        let len = candidates.len() as i32;
+        let mut selected = self
+            .by_set_pos
+            .iter()
+            .filter_map(|position| {
+                let index = if *position > 0 { position - 1 } else { len + position };
+                usize::try_from(index).ok().and_then(|index| candidates.get(index)).copied()
+            })
+            .collect::<Vec<_>>();
+        selected.sort();
+        selected.dedup();
+
+        Some((begin, selected))
+    }
+
+    /// Index of the period from `start` containing the local date time `target`, or the last one before it
+    fn index_at(&self, start: NaiveDateTime, target: NaiveDateTime) -> Option<i64> {
+        let units = match self.frequency {
+            Frequency::Yearly => i64::from(target.year()) - i64::from(start.year()),
+            Frequency::Monthly => {
+                (i64::from(target.year()) - i64::from(start.year())) * 12 + i64::from(target.month()) - i64::from(start.month())
+            }
+            Frequency::Weekly => {
+                (previous_or_same(target.date(), self.week_start)? - previous_or_same(start.date(), self.week_start)?).num_days() / 7
+            }
+            Frequency::Daily => (target.date() - start.date()).num_days(),
+            Frequency::Hourly => (target - start).num_seconds().div_euclid(3_600),
+            Frequency::Minutely => (target - start).num_seconds().div_euclid(60),
+            Frequency::Secondly => (target - start).num_seconds(),
+        };
+
+        Some(units.div_euclid(i64::from(self.interval)).max(0))
+    }
+
+    /// Earliest local date time after an empty period beginning at `begin` that may match, for daily and shorter rules
+    ///
+    /// Skips to the next month, day, hour or minute when the one of `begin` is ruled out by a BY* part.
+    fn next_candidate(&self, begin: NaiveDateTime, dtstart: NaiveDate) -> Option<NaiveDateTime> {
+        if self.frequency < Frequency::Daily {
+            return None;
+        }
+
+        let date = begin.date();
+        if !self.by_month.is_empty() && !self.by_month.contains(&date.month()) {
+            return Some(date.with_day(1)?.checked_add_months(Months::new(1))?.and_time(NaiveTime::MIN));
+        }
+        if !self.matches(date, dtstart) {
+            return Some(date.succ_opt()?.and_time(NaiveTime::MIN));
+        }
+
+        let hour = begin.date().and_hms_opt(begin.hour(), 0, 0)?;
+        if self.frequency >= Frequency::Hourly && !self.by_hour.is_empty() && !self.by_hour.contains(&begin.hour()) {
+            return hour.checked_add_signed(TimeDelta::hours(1));
+        }
+
+        let minute = hour.with_minute(begin.minute())?;
+        if self.frequency >= Frequency::Minutely && !self.by_minute.is_empty() && !self.by_minute.contains(&begin.minute()) {
+            return minute.checked_add_signed(TimeDelta::minutes(1));
+        }
+
+        None
+    }
+
+    /// Whether a date of a period matches the BY* parts of the rule, or the date of `dtstart` when there are none
+    fn matches(&self, date: NaiveDate, dtstart: NaiveDate) -> bool {
+        let days_in_year = if date.leap_year() { 366 } else { 365 };
+        let days_in_month = date
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.with_day(1))
+            .and_then(|next| next.pred_opt())
+            .map_or(31, |last| last.day());
+
+        let at = |n: i32, position: u32, len: u32| {
+            if n > 0 {
+                n as u32 == position
+            } else {
+                (len + 1).checked_sub(n.unsigned_abs()) == Some(position)
+            }
+        };
+
+        if !self.by_month.is_empty() && !self.by_month.contains(&date.month()) {
+            return false;
+        }
+
+        if !self.by_year_day.is_empty() && !self.by_year_day.iter().any(|n| at(*n, date.ordinal(), days_in_year)) {
+            return false;
+        }
+
+        if !self.by_month_day.is_empty() && !self.by_month_day.iter().any(|n| at(*n, date.day(), days_in_month)) {
+            return false;
+        }
+
+        if !self.by_day.is_empty() {
+            // Ordinals count within the year, or within the month for monthly rules and yearly rules by month
+            let (position, len) = match self.frequency {
+                Frequency::Yearly if self.by_month.is_empty() => (date.ordinal(), days_in_year),
+                Frequency::Yearly | Frequency::Monthly => (date.day(), days_in_month),
+                _ => (0, 0),
+            };
+
+            let matched = self.by_day.iter().any(|(nth, weekday)| {
+                *weekday == date.weekday()
+                    && match nth {
+                        Some(nth) if len > 0 && *nth > 0 => (position - 1) / 7 + 1 == nth.unsigned_abs(),
+                        Some(nth) if len > 0 => (len - position) / 7 + 1 == nth.unsigned_abs(),
+                        _ => true,
+                    }
+            });
+
+            if !matched {
+                return false;
+            }
+        }
+
+        if self.by_year_day.is_empty() && self.by_month_day.is_empty() && self.by_day.is_empty() {
+            return match self.frequency {
+                Frequency::Yearly if self.by_month.is_empty() => (date.month(), date.day()) == (dtstart.month(), dtstart.day()),
+                Frequency::Yearly | Frequency::Monthly => date.day() == dtstart.day(),
+                Frequency::Weekly => date.weekday() == dtstart.weekday(),
+                _ => true,
+            };
+        }
+
+        true
+    }
+}
+
+/// Iterator over the occurrences of an [`RRule`], see [`RRule::occurrences`]
+#[derive(Clone, Debug)]
+pub struct Occurrences<'a> {
+    rule: &'a RRule,
+    timezone: Timezone,
+    /// `DTSTART` in the local time of the timezone
+    start: NaiveDateTime,
+    period: i64,
+    /// Local date time of the last occurrence, or where the expansion was skipped to
+    last: NaiveDateTime,
+    empty_periods: u32,
+    scanned: u32,
+    emitted: u32,
+    pending: VecDeque<DateTime<FixedOffset>>,
+    done: bool,
+    exhausted: bool,
+}
+
+impl Occurrences<'_> {
+    /// Skips the periods entirely before `from`, unless the rule has a `COUNT`, which has to be expanded from `DTSTART`
+    ///
+    /// Occurrences before `from` within its period are still produced.
+    pub(crate) fn skip_to(mut self, from: DateTime<FixedOffset>) -> Self {
+        if self.rule.count.is_some() {
+            return self;
+        }
+
+        let from = self.timezone.convert(from.to_utc()).naive_local();
+        if let Some(period) = self.rule.index_at(self.start, from).filter(|period| *period > self.period) {
+            self.period = period;
+            self.last = self.last.max(from);
+        }
+
+        self
+    }
+
+    /// Whether the iterator gave up after scanning too many periods, rather than running out of occurrences
+    pub(crate) fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = DateTime<FixedOffset>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rule.count.is_some_and(|count| self.emitted >= count) {
+                return None;
+            }
+
+            if let Some(occurrence) = self.pending.pop_front() {
+                self.emitted += 1;
+                return Some(occurrence);
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if self.scanned >= MAX_PERIODS {
+                self.done = true;
+                self.exhausted = true;
+                return None;
+            }
+            self.scanned += 1;
+
+            let Some((begin, candidates)) = self.rule.period(self.start, self.period) else {
+                self.done = true;
+                continue;
+            };
+
+            for candidate in candidates.into_iter().filter(|candidate| *candidate >= self.start) {
+                let Some(occurrence) = self.timezone.localize(candidate) else {
+                    continue;
+                };
+
+                let past_until = match self.rule.until {
+                    Some(Until::Local(until)) => candidate > until,
+                    Some(Until::Utc(until)) => occurrence.to_utc() > until,
+                    None => false,
+                };
+                if past_until {
+                    self.done = true;
+                    break;
+                }
+
+                self.pending.push_back(occurrence);
+                self.last = candidate;
+            }
+
+            if !self.pending.is_empty() {
+                self.period += 1;
+                self.empty_periods = 0;
+                continue;
+            }
+
+            self.empty_periods += 1;
+            if self.empty_periods >= MIN_EMPTY_PERIODS && (begin - self.last).num_days() > MAX_EMPTY_DAYS {
+                self.done = true;
+                continue;
+            }
+
+            // jumps over the periods that can't match, so short frequencies with long gaps end quickly
+            self.period = self
+                .rule
+                .next_candidate(begin, self.start.date())
+                .and_then(|candidate| self.rule.index_at(self.start, candidate))
+                .map_or(self.period + 1, |period| period.max(self.period + 1));
+        }
+    }
+}
+
+/// Error produced by an invalid [`RRule`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RRuleError(String);
+
+impl Display for RRuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RRuleError {}
+
+/// Parses an unsigned number of a rule part within a range
+fn number(key: &str, value: &str, range: std::ops::RangeInclusive<u32>) -> Result<u32, RRuleError> {
+    value
+        .parse()
+        .ok()
+        .filter(|number| range.contains(number))
+        .ok_or_else(|| RRuleError(format!("Invalid {}: {}", key, value)))
+}
+
+/// Parses a non-zero signed number of a rule part, at most `max` either way
+fn signed(key: &str, value: &str, max: u32) -> Result<i32, RRuleError> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|number| *number != 0 && number.unsigned_abs() <= max)
+        .ok_or_else(|| RRuleError(format!("Invalid {}: {}", key, value)))
+}
+
+/// Parses the comma separated values of a rule part
+fn list<T>(key: &str, value: &str, parse: impl Fn(&str) -> Result<T, RRuleError>) -> Result<Vec<T>, RRuleError> {
+    let values = value.split(',').map(parse).collect::<Result<Vec<_>, _>>()?;
+
+    if values.is_empty() {
+        return Err(RRuleError(format!("Empty {}", key)));
+    }
+
+    Ok(values)
+}
+
+/// Parses a two letter weekday, as in `MO`
+fn weekday(value: &str) -> Result<Weekday, RRuleError> {
+    match value {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(RRuleError(format!("Invalid weekday: {}", value))),
+    }
+}
+
+/// Parses a weekday of `BYDAY` with its optional ordinal, as in `MO`, `2TU` or `-1FR`
+fn nth_weekday(value: &str) -> Result<(Option<i32>, Weekday), RRuleError> {
+    let split = value
+        .len()
+        .checked_sub(2)
+        .filter(|split| value.is_char_boundary(*split))
+        .ok_or_else(|| RRuleError(format!("Invalid BYDAY: {}", value)))?;
+    let (nth, day) = value.split_at(split);
+
+    let nth = match nth {
+        "" => None,
+        nth => Some(signed("BYDAY", nth.trim_start_matches('+'), 53)?),
+    };
+
+    Ok((nth, weekday(day)?))
+}
+
+/// Parses `UNTIL`, a date, a local date time, or a UTC date time ending with `Z`
+fn until(value: &str) -> Result<Until, RRuleError> {
+    let invalid = |_e| RRuleError(format!("Invalid UNTIL: {}", value));
+
+    if let Some(value) = value.strip_suffix('Z') {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map(|until| Until::Utc(until.and_utc()))
+            .map_err(invalid)
+    } else if value.contains('T') {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map(Until::Local)
+            .map_err(invalid)
+    } else {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(|until| Until::Local(until.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default())))
+            .map_err(invalid)
+    }
+}
+
+/// `each_occurrence` block helper, iterates over the occurrences of a recurrence rule, as in `{{#each_occurrence rule start limit=5}}`
+///
+/// The first parameter is an RRULE, the second one its DTSTART, a UNIX timestamp in seconds, RFC3339 or RFC2822 date
+/// time. Occurrences start at `from`, now by default, up to `to` and at most `limit`. The rule is expanded in
+/// `timezone`, by default the timezone of the helper or UTC.
+#[derive(Clone)]
+pub(crate) struct EachOccurrence {
+    pub helper: Arc<HandlebarsChronoDateTime>,
+}
+
+impl EachOccurrence {
+    fn items(&self, h: &Helper) -> Result<Vec<JsonValue>, String> {
+        let (rule, dtstart) = match (h.param(0), h.param(1)) {
+            (Some(rule), Some(dtstart)) => (
+                rule.value().render().parse::<RRule>().map_err(|e| format!("Invalid rule: {}", e))?,
+                instant(&self.helper, Some(dtstart.value()))?,
+            ),
+            _ => return Err("Missing parameters of `each_occurrence`, expected the rule and its start".to_string()),
+        };
+
+        let hash = |key| h.hash_get(key).map(|value| value.value());
+
+        let from = instant(&self.helper, hash("from"))?;
+        let to = hash("to").map(|to| instant(&self.helper, Some(to))).transpose()?;
+        let limit = hash("limit")
+            .map(|limit| {
+                limit
+                    .render()
+                    .parse::<usize>()
+                    .map_err(|e| format!("Invalid limit parameter: {}", e))
+            })
+            .transpose()?;
+
+        if to.is_none() && limit.is_none() && !rule.is_finite() {
+            return Err("Missing `to` or `limit` hash parameter for a rule without COUNT or UNTIL".to_string());
+        }
+
+        let timezone = match hash("timezone") {
+            Some(timezone) => timezone.render().parse()?,
+            None => self.helper.timezone().unwrap_or(Timezone::Fixed(Utc.fix())),
+        };
+
+        let to_parts = hash("to_parts").is_some_and(|to_parts| to_parts.is_truthy(false));
+
+        let mut iterator = rule.occurrences(dtstart, timezone).skip_to(from);
+        let occurrences = iterator
+            .by_ref()
+            .skip_while(|occurrence| *occurrence < from)
+            .take_while(|occurrence| to.is_none_or(|to| *occurrence <= to))
+            .take(limit.unwrap_or(usize::MAX).min(MAX_OCCURRENCES + 1))
+            .collect::<Vec<_>>();
+
+        if iterator.exhausted() {
+            return Err(format!(
+                "Too many periods, at most {} can be scanned from the start of the rule",
+                MAX_PERIODS
+            ));
+        }
+
+        if occurrences.len() > MAX_OCCURRENCES {
+            return Err(format!("Too many occurrences, at most {} can be iterated", MAX_OCCURRENCES));
+        }
+
+        Ok(occurrences
+            .iter()
+            .map(|occurrence| {
+                if to_parts {
+                    parts(occurrence)
+                } else {
+                    JsonValue::String(occurrence.to_rfc3339())
+                }
+            })
+            .collect())
+    }
+}
+
+impl HelperDef for EachOccurrence {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        render_or_recover(&self.helper, self.items(h), h, r, ctx, rc, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+    use serde_json::json;
+
+    fn occurrences(rule: &str, dtstart: &str) -> Vec<String> {
+        let dtstart = DateTime::parse_from_rfc3339(dtstart).expect("Parse error");
+
+        rule.parse::<RRule>()
+            .expect("Rule error")
+            .occurrences(dtstart, Timezone::Fixed(*dtstart.offset()))
+            .take(10)
+            .map(|occurrence| occurrence.format("%F %T").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_works() {
+        for (rule, dtstart, comparison) in [
+            (
+                "FREQ=DAILY;COUNT=3",
+                "1997-09-02T09:00:00-04:00",
+                vec!["1997-09-02 09:00:00", "1997-09-03 09:00:00", "1997-09-04 09:00:00"],
+            ),
+            (
+                "RRULE:FREQ=WEEKLY;INTERVAL=2;WKST=SU;BYDAY=TU,TH;COUNT=4",
+                "1997-09-02T09:00:00-04:00",
+                vec![
+                    "1997-09-02 09:00:00",
+                    "1997-09-04 09:00:00",
+                    "1997-09-16 09:00:00",
+                    "1997-09-18 09:00:00",
+                ],
+            ),
+            (
+                "FREQ=MONTHLY;BYDAY=-1FR;COUNT=3",
+                "2024-01-01T09:00:00Z",
+                vec!["2024-01-26 09:00:00", "2024-02-23 09:00:00", "2024-03-29 09:00:00"],
+            ),
+            (
+                "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1;COUNT=3",
+                "2024-01-01T09:00:00Z",
+                vec!["2024-01-31 09:00:00", "2024-02-29 09:00:00", "2024-03-29 09:00:00"],
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=31;COUNT=3",
+                "2024-01-01T09:00:00Z",
+                vec!["2024-01-31 09:00:00", "2024-03-31 09:00:00", "2024-05-31 09:00:00"],
+            ),
+            (
+                "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29;COUNT=2",
+                "2024-01-01T09:00:00Z",
+                vec!["2024-02-29 09:00:00", "2028-02-29 09:00:00"],
+            ),
+            (
+                "FREQ=YEARLY;BYDAY=20MO;COUNT=1",
+                "1997-01-01T09:00:00Z",
+                vec!["1997-05-19 09:00:00"],
+            ),
+            (
+                "FREQ=YEARLY;BYYEARDAY=-1;COUNT=2",
+                "2024-06-01T09:00:00Z",
+                vec!["2024-12-31 09:00:00", "2025-12-31 09:00:00"],
+            ),
+            (
+                "FREQ=YEARLY;BYMONTH=1;BYDAY=SU;BYSETPOS=1,-1;UNTIL=20250101",
+                "2024-01-01T09:00:00Z",
+                vec!["2024-01-07 09:00:00", "2024-01-28 09:00:00"],
+            ),
+            (
+                "FREQ=HOURLY;INTERVAL=3;UNTIL=19970902T170000Z",
+                "1997-09-02T09:00:00Z",
+                vec!["1997-09-02 09:00:00", "1997-09-02 12:00:00", "1997-09-02 15:00:00"],
+            ),
+            (
+                "FREQ=MINUTELY;INTERVAL=20;BYHOUR=9,10;COUNT=4",
+                "1997-09-02T09:00:00Z",
+                vec![
+                    "1997-09-02 09:00:00",
+                    "1997-09-02 09:20:00",
+                    "1997-09-02 09:40:00",
+                    "1997-09-02 10:00:00",
+                ],
+            ),
+            (
+                "FREQ=DAILY;BYHOUR=9,17;BYMINUTE=30;COUNT=3",
+                "1997-09-02T10:00:00Z",
+                vec!["1997-09-02 17:30:00", "1997-09-03 09:30:00", "1997-09-03 17:30:00"],
+            ),
+            ("FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30", "2024-01-01T09:00:00Z", vec![]),
+            (
+                "FREQ=MINUTELY;BYMONTH=12;COUNT=2",
+                "2024-01-01T00:00:00Z",
+                vec!["2024-12-01 00:00:00", "2024-12-01 00:01:00"],
+            ),
+            (
+                "FREQ=SECONDLY;BYHOUR=23;BYMINUTE=59;BYSECOND=59;COUNT=2",
+                "2024-01-01T00:00:00Z",
+                vec!["2024-01-01 23:59:59", "2024-01-02 23:59:59"],
+            ),
+            ("FREQ=MINUTELY;BYMONTHDAY=30;BYMONTH=2", "2024-01-01T09:00:00Z", vec![]),
+        ] {
+            assert_eq!(occurrences(rule, dtstart), comparison, "Failed to expand {}", rule);
+        }
+
+        let date = |datetime| DateTime::parse_from_rfc3339(datetime).expect("Parse error");
+        let rule = "FREQ=WEEKLY;BYDAY=MO".parse::<RRule>().expect("Rule error");
+        let utc = Timezone::Fixed(Utc.fix());
+        assert_eq!(
+            rule.after(date("2024-01-01T09:00:00Z"), utc, date("2024-01-10T00:00:00Z")),
+            Some(date("2024-01-15T09:00:00Z"))
+        );
+        assert_eq!(
+            rule.after(date("2024-01-01T09:00:00Z"), utc, date("2024-01-15T09:00:00Z")),
+            Some(date("2024-01-22T09:00:00Z"))
+        );
+        assert!(!rule.is_finite());
+
+        let rule = "FREQ=MINUTELY;BYMONTH=12".parse::<RRule>().expect("Rule error");
+        assert_eq!(
+            rule.after(date("2024-01-01T00:00:00Z"), utc, date("2024-01-01T00:00:00Z")),
+            Some(date("2024-12-01T00:00:00Z"))
+        );
+        let rule = "FREQ=MINUTELY".parse::<RRule>().expect("Rule error");
+        assert_eq!(
+            rule.after(date("1970-01-01T00:00:00Z"), utc, date("2024-06-01T12:34:56Z")),
+            Some(date("2024-06-01T12:35:00Z"))
+        );
+
+        #[cfg(feature = "timezone")]
+        {
+            let sofia = "Europe/Sofia".parse::<Timezone>().expect("Timezone error");
+
+            for (rule, dtstart, comparison) in [
+                (
+                    "FREQ=DAILY;COUNT=3",
+                    "2024-03-30T09:00:00+02:00",
+                    [
+                        "2024-03-30T09:00:00+02:00",
+                        "2024-03-31T09:00:00+03:00",
+                        "2024-04-01T09:00:00+03:00",
+                    ],
+                ),
+                (
+                    "FREQ=DAILY;BYHOUR=3;BYMINUTE=30;COUNT=3",
+                    "2024-03-30T03:30:00+02:00",
+                    [
+                        "2024-03-30T03:30:00+02:00",
+                        "2024-03-31T04:30:00+03:00",
+                        "2024-04-01T03:30:00+03:00",
+                    ],
+                ),
+            ] {
+                assert_eq!(
+                    rule.parse::<RRule>()
+                        .expect("Rule error")
+                        .occurrences(date(dtstart), sofia)
+                        .map(|occurrence| occurrence.to_rfc3339())
+                        .collect::<Vec<_>>(),
+                    comparison,
+                    "Failed to expand {} in Europe/Sofia",
+                    rule
+                );
+            }
+        }
+
+        let h = registry();
+        let data = json!({"rule": "FREQ=WEEKLY;BYDAY=MO,WE", "start": "2024-01-01T18:00:00Z"});
+
+        for (template, comparison) in [
+            (
+                r#"{{#each_occurrence rule start from="2024-01-05T00:00:00Z" limit=3}}{{this}} {{/each_occurrence}}"#,
+                "2024-01-08T18:00:00+00:00 2024-01-10T18:00:00+00:00 2024-01-15T18:00:00+00:00 ",
+            ),
+            (
+                r#"{{#each_occurrence rule start from=start to="2024-01-10T18:00:00Z" to_parts=true}}{{day}}{{#unless @last}},{{/unless}}{{/each_occurrence}}"#,
+                "1,3,8,10",
+            ),
+            (
+                r#"{{#each_occurrence rule start from=start limit=2 timezone="+02:00" as |date|}}{{date}} {{/each_occurrence}}"#,
+                "2024-01-01T20:00:00+02:00 2024-01-03T20:00:00+02:00 ",
+            ),
+            (
+                r#"{{#each_occurrence "FREQ=DAILY;COUNT=2" start}}{{this}}{{else}}over{{/each_occurrence}}"#,
+                "over",
+            ),
+            (
+                r#"{{#each_occurrence "FREQ=MINUTELY;BYMONTH=12" start from=start limit=1}}{{this}}{{/each_occurrence}}"#,
+                "2024-12-01T00:00:00+00:00",
+            ),
+            (
+                r#"{{#each_occurrence "FREQ=SECONDLY" 0 from="2024-01-01T00:00:00Z" limit=2}}{{this}} {{/each_occurrence}}"#,
+                "2024-01-01T00:00:00+00:00 2024-01-01T00:00:01+00:00 ",
+            ),
+        ] {
+            assert_eq!(
+                h.render_template(template, &data).expect("Render error"),
+                comparison,
+                "Failed to render {}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn it_craps() {
+        for rule in [
+            "",
+            "COUNT=3",
+            "FREQ=FORTNIGHTLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=3;UNTIL=20240101",
+            "FREQ=DAILY;UNTIL=2024",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=MONTHLY;BYDAY=1XX",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=DAILY;BYHOUR=24",
+            "FREQ=YEARLY;BYWEEKNO=20",
+            "FREQ=DAILY;COLOR=RED",
+            "FREQ",
+        ] {
+            assert!(matches!(rule.parse::<RRule>(), Err(_e),), "Failed to produce error with {}", rule);
+        }
+
+        let h = registry();
+
+        for template in [
+            r#"{{#each_occurrence "FREQ=DAILY"}}{{/each_occurrence}}"#,
+            r#"{{#each_occurrence "FREQ=DAILY" 618658211}}{{/each_occurrence}}"#,
+            r#"{{#each_occurrence "FREQ=SOMETIMES" 618658211 limit=1}}{{/each_occurrence}}"#,
+            r#"{{#each_occurrence "FREQ=DAILY" "yesterday" limit=1}}{{/each_occurrence}}"#,
+            r#"{{#each_occurrence "FREQ=DAILY" 618658211 limit="all"}}{{/each_occurrence}}"#,
+            r#"{{#each_occurrence "FREQ=SECONDLY" 618658211 from=618658211 to=618758211}}{{/each_occurrence}}"#,
+            r#"{{#each_occurrence "FREQ=SECONDLY;COUNT=4000000000" 0 from="2024-01-01T00:00:00Z" limit=1}}{{/each_occurrence}}"#,
+        ] {
+            assert!(
+                matches!(h.render_template(template, &json!({})), Err(_e),),
+                "Failed to produce error with {}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,861 @@
+use chrono::{DateTime, Days, FixedOffset, Local, LocalResult, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc, Weekday};
+#[cfg(feature = "timezone")]
+use chrono_tz::Tz;
+use handlebars::{JsonRender, JsonValue};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::age::AgePrecision;
+use crate::calendar::MonthOverflow;
+use crate::calendar_time::CalendarFormats;
+use crate::cron::{Cron, CronOccurrence};
+use crate::datetime::HandlebarsChronoDateTime;
+use crate::format::FormatSyntax;
+use crate::iso_duration::IsoDuration;
+use crate::rrule::RRule;
+use crate::styles::Style;
+use crate::weekday::{parse_weekday, parse_weekdays, NthWeekday};
+
+const NANOS_PER_MICROSECOND: i128 = 1_000;
+pub(crate) const NANOS_PER_MILLISECOND: i128 = 1_000_000;
+pub(crate) const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const NANOS_PER_MINUTE: i128 = 60 * NANOS_PER_SECOND;
+const NANOS_PER_HOUR: i128 = 60 * NANOS_PER_MINUTE;
+
+/// Target of the `with_timezone` modifier, parsed from `local`, a fixed offset or an IANA timezone name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timezone {
+    /// Offset of the system timezone at the time of rendering
+    Local,
+    /// Fixed offset, as in `+02:00`
+    Fixed(FixedOffset),
+    /// IANA timezone, as in `Europe/Sofia`
+    #[cfg(feature = "timezone")]
+    Iana(Tz),
+}
+
+impl Timezone {
+    /// Converts a UTC date time to this timezone
+    pub(crate) fn convert(&self, datetime: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Self::Local => datetime.with_timezone(&Local::now().fixed_offset().timezone()),
+            Self::Fixed(tz) => datetime.with_timezone(tz),
+            #[cfg(feature = "timezone")]
+            Self::Iana(tz) => datetime.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// Resolves a local date time in this timezone, see [`localize`]
+    pub(crate) fn localize(&self, datetime: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Self::Local => localize(&Local, datetime),
+            Self::Fixed(tz) => localize(tz, datetime),
+            #[cfg(feature = "timezone")]
+            Self::Iana(tz) => localize(tz, datetime),
+        }
+    }
+}
+
+/// Resolves a local date time, as RFC 5545 does for recurrences
+///
+/// Ambiguous times take the earliest offset, and times skipped by a DST gap take the offset before the gap,
+/// so 02:30 skipped from 02:00 to 03:00 becomes 03:30.
+fn localize<Tz: TimeZone>(tz: &Tz, datetime: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    match tz.from_local_datetime(&datetime) {
+        LocalResult::Single(resolved) | LocalResult::Ambiguous(resolved, _) => Some(resolved.fixed_offset()),
+        LocalResult::None => {
+            let before = tz
+                .offset_from_local_datetime(&datetime.checked_sub_days(Days::new(1))?)
+                .earliest()?
+                .fix();
+
+            before
+                .from_local_datetime(&datetime)
+                .single()
+                .map(|resolved| resolved.with_timezone(tz).fixed_offset())
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(timezone: &str) -> Result<Self, Self::Err> {
+        if timezone.to_lowercase() == "local" {
+            Ok(Self::Local)
+        } else if timezone.contains('0') {
+            FixedOffset::from_str(timezone).map(Self::Fixed).map_err(|_e| {
+                "Failed to parse timezone offset. Supported values are IANA timezones, local or valid fixed offset".to_string()
+            })
+        } else {
+            #[cfg(feature = "timezone")]
+            return timezone.parse::<Tz>().map(Self::Iana).map_err(|_e| {
+                "Failed to parse IANA timezone. Supported values are IANA timezones, local or valid fixed offset".to_string()
+            });
+
+            #[cfg(not(feature = "timezone"))]
+            Err("You need to enable the `timezone` feature of the `handlebars-chrono` create for IANA timezones to work.".to_string())
+        }
+    }
+}
+
+/// Where the `DateTime` comes from, `Now` if no initializer is given
+///
+/// Whatever the initializer, the `DateTime` is converted to UTC before the modifiers run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Initializer {
+    /// Current time, the default
+    #[default]
+    Now,
+    /// `from_timestamp`, UNIX timestamp in seconds
+    Timestamp(i64),
+    /// `from_timestamp_millis`, UNIX timestamp in milli-seconds
+    TimestampMillis(i64),
+    /// `from_timestamp_micros`, UNIX timestamp in micro-seconds
+    TimestampMicros(i64),
+    /// `from_timestamp_nanos`, UNIX timestamp in nano-seconds
+    TimestampNanos(i64),
+    /// `from_rfc2822`
+    Rfc2822(DateTime<FixedOffset>),
+    /// `from_rfc3339`
+    Rfc3339(DateTime<FixedOffset>),
+    /// `from_str` + `input_format`, a date time string and its strftime format
+    Str { input: String, format: String },
+    /// `from_rrule` + `dtstart`, the next occurrence of a recurrence rule after `after`, now by default
+    ///
+    /// The rule is expanded in the `with_timezone` timezone, the default timezone of the helper, or UTC.
+    RRule {
+        rule: Box<RRule>,
+        dtstart: DateTime<FixedOffset>,
+        after: Option<DateTime<FixedOffset>>,
+    },
+    /// `from_cron` + optional `occurrence`, the next or previous occurrence of a cron expression relative to `after`,
+    /// now by default
+    ///
+    /// The expression is evaluated in the `with_timezone` timezone, the default timezone of the helper, or UTC.
+    Cron {
+        cron: Cron,
+        occurrence: CronOccurrence,
+        after: Option<DateTime<FixedOffset>>,
+    },
+}
+
+impl Initializer {
+    /// Initializer of a date time given as a helper parameter: a UNIX timestamp in seconds, RFC3339 or RFC2822
+    pub(crate) fn from_value(value: &JsonValue) -> Result<Self, String> {
+        match value {
+            JsonValue::Number(number) => number
+                .as_i64()
+                .map(Self::Timestamp)
+                .ok_or_else(|| format!("Invalid seconds timestamp: {}", number)),
+            JsonValue::String(input) => {
+                if let Ok(timestamp) = input.parse() {
+                    Ok(Self::Timestamp(timestamp))
+                } else if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+                    Ok(Self::Rfc3339(datetime))
+                } else {
+                    DateTime::parse_from_rfc2822(input)
+                        .map(Self::Rfc2822)
+                        .map_err(|_e| format!("Invalid date time, expected a UNIX timestamp, RFC3339 or RFC2822: {}", input))
+                }
+            }
+            value => Err(format!(
+                "Invalid date time, expected a UNIX timestamp, RFC3339 or RFC2822: {}",
+                value
+            )),
+        }
+    }
+}
+
+/// Relative shift of either the `add_*` or the `sub_*` modifiers, applied in field order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Shift {
+    pub years: Option<i64>,
+    pub quarters: Option<i64>,
+    pub months: Option<i64>,
+    pub weeks: Option<TimeDelta>,
+    pub days: Option<i64>,
+    pub business_days: Option<i64>,
+    pub hours: Option<TimeDelta>,
+    pub minutes: Option<TimeDelta>,
+    pub seconds: Option<TimeDelta>,
+    pub milliseconds: Option<TimeDelta>,
+    pub microseconds: Option<TimeDelta>,
+    pub nanoseconds: Option<TimeDelta>,
+}
+
+/// Modifiers, applied in field order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Modifiers {
+    pub timezone: Option<Timezone>,
+    pub ordinal: Option<u32>,
+    pub ordinal0: Option<u32>,
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub month0: Option<u32>,
+    pub day: Option<u32>,
+    pub day0: Option<u32>,
+    pub hour: Option<u32>,
+    pub minute: Option<u32>,
+    pub second: Option<u32>,
+    pub nanosecond: Option<u32>,
+    pub next_weekday: Option<Weekday>,
+    pub previous_weekday: Option<Weekday>,
+    pub nth_weekday_of_month: Option<NthWeekday>,
+    pub add: Shift,
+    pub sub: Shift,
+    pub add_duration: Option<IsoDuration>,
+    pub sub_duration: Option<IsoDuration>,
+    /// Overrides the weekend days of the helper
+    pub weekend: Option<Vec<Weekday>>,
+    pub month_overflow: MonthOverflow,
+}
+
+/// How the `DateTime` gets rendered, `Rfc3339` if no finalizer is given
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Finalizer {
+    /// RFC3339, the default
+    #[default]
+    Rfc3339,
+    /// `to_rfc2822`
+    Rfc2822,
+    /// `to_timestamp`, UNIX timestamp in seconds
+    Timestamp,
+    /// `to_timestamp_millis`, UNIX timestamp in milli-seconds
+    TimestampMillis,
+    /// `to_timestamp_micros`, UNIX timestamp in micro-seconds
+    TimestampMicros,
+    /// `to_timestamp_nanos`, UNIX timestamp in nano-seconds
+    TimestampNanos,
+    /// `output_format` + `format_syntax`, in the syntax of the helper unless given
+    OutputFormat { format: String, syntax: Option<FormatSyntax> },
+    /// `format`, the name of a registered or built-in preset
+    Preset(String),
+    /// `date_style` and/or `time_style`
+    Style { date: Option<Style>, time: Option<Style> },
+    /// `years_since`, full years since the given date time
+    YearsSince(DateTime<FixedOffset>),
+    /// `business_days_until`, business days up to and including the given date time
+    BusinessDaysUntil(DateTime<FixedOffset>),
+    /// `to_calendar` + `calendar_base`, as in `Yesterday at 9:30 AM`, relative to now unless a base is given
+    Calendar {
+        base: Option<DateTime<FixedOffset>>,
+        formats: CalendarFormats,
+    },
+    /// `age_since` + `age_precision`, `age_output` and `age_separator`, years, months and days since the given date time
+    Age {
+        since: DateTime<FixedOffset>,
+        precision: AgePrecision,
+        json: bool,
+        separator: Option<String>,
+    },
+}
+
+/// Parsed parameters of the `datetime` helper: an initializer, the modifiers and a finalizer
+///
+/// Lets Rust code create and format dates exactly the way templates do, without going through Handlebars.
+///
+/// ```rust
+/// use handlebars::JsonValue;
+/// use handlebars_chrono::{DateTimeSpec, Finalizer, Initializer};
+/// use std::collections::BTreeMap;
+///
+/// let spec = DateTimeSpec::new()
+///     .with_initializer(Initializer::Timestamp(618658211))
+///     .with_modifier("add_days", "2")
+///     .expect("Invalid modifier")
+///     .with_finalizer(Finalizer::OutputFormat {
+///         format: "%B %@o, %Y".to_string(),
+///         syntax: None,
+///     });
+///
+/// assert_eq!(spec.render().expect("Render error"), "August 11th, 1989");
+///
+/// let params = BTreeMap::from([
+///     ("from_timestamp", JsonValue::from(618658211)),
+///     ("add_days", JsonValue::from(2)),
+///     ("output_format", JsonValue::from("%B %@o, %Y")),
+/// ]);
+///
+/// assert_eq!(DateTimeSpec::from_map(&params).expect("Invalid parameters"), spec);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DateTimeSpec {
+    pub(crate) initializer: Initializer,
+    pub(crate) modifiers: Modifiers,
+    pub(crate) finalizer: Finalizer,
+    pub(crate) locale: Option<String>,
+}
+
+impl DateTimeSpec {
+    /// Spec of the current time in RFC3339, same as `{{datetime}}`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the hash parameters of a template, as in `{"from_timestamp": 618658211, "output_format": "%Y"}`
+    ///
+    /// Values are converted to strings the same way Handlebars does.
+    pub fn from_map(params: &BTreeMap<&str, JsonValue>) -> Result<Self, SpecError> {
+        Self::parse(params.iter().map(|(key, value)| (*key, value.render()))).map_err(SpecError)
+    }
+
+    /// Sets where the `DateTime` comes from
+    pub fn with_initializer(mut self, initializer: Initializer) -> Self {
+        self.initializer = initializer;
+        self
+    }
+
+    /// Sets a modifier by its hash parameter name, as in `add_days` and `3`
+    ///
+    /// Modifiers are applied in the documented order, regardless of the order they are set in.
+    pub fn with_modifier(mut self, name: &str, value: &str) -> Result<Self, SpecError> {
+        self.modifiers
+            .set(name, value)
+            .ok_or_else(|| SpecError(format!("Unknown modifier: {}", name)))?
+            .map_err(SpecError)?;
+
+        Ok(self)
+    }
+
+    /// Sets how the `DateTime` gets rendered
+    pub fn with_finalizer(mut self, finalizer: Finalizer) -> Self {
+        self.finalizer = finalizer;
+        self
+    }
+
+    /// Sets the locale of the formatting finalizers
+    pub fn with_locale<L: Into<String>>(mut self, locale: L) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Runs the initializer and the modifiers, with the default configuration of the helper
+    pub fn evaluate(&self) -> Result<DateTime<FixedOffset>, SpecError> {
+        self.evaluate_with(&HandlebarsChronoDateTime::default())
+    }
+
+    /// Runs the initializer, the modifiers and the finalizer, with the default configuration of the helper
+    pub fn render(&self) -> Result<String, SpecError> {
+        self.render_with(&HandlebarsChronoDateTime::default())
+    }
+
+    /// Runs the initializer and the modifiers, with the weekend, holidays and formats of the given helper
+    pub fn evaluate_with(&self, helper: &HandlebarsChronoDateTime) -> Result<DateTime<FixedOffset>, SpecError> {
+        helper.evaluate(self).map_err(SpecError)
+    }
+
+    /// Runs the initializer, the modifiers and the finalizer, with the weekend, holidays and formats of the given helper
+    ///
+    /// Reusing the same helper also reuses its cache of compiled formats.
+    pub fn render_with(&self, helper: &HandlebarsChronoDateTime) -> Result<String, SpecError> {
+        helper.render(self).map_err(SpecError)
+    }
+
+    /// Parses the hash parameters in a single pass, failing on unknown parameters
+    ///
+    /// When several initializers or finalizers are given, the first one in the documented order wins.
+    pub(crate) fn parse<'a, I: IntoIterator<Item = (&'a str, String)>>(params: I) -> Result<Self, String> {
+        let mut spec = Self::default();
+
+        // initializers, ordered by precedence
+        let mut initializers: [Option<Initializer>; 9] = Default::default();
+        let mut input_format = None;
+        let mut dtstart = None;
+        let mut after = None;
+        let mut occurrence = None;
+
+        // finalizers
+        let mut output_format = None;
+        let mut format_syntax = None;
+        let mut preset = None;
+        let mut date_style = None;
+        let mut time_style = None;
+        let mut flags: [Option<Finalizer>; 5] = Default::default();
+        let mut years_since = None;
+        let mut business_days_until = None;
+        let mut to_calendar = false;
+        let mut calendar_base = None;
+        let mut calendar_formats = CalendarFormats::default();
+        let mut age_since = None;
+        let mut age_precision = None;
+        let mut age_json = false;
+        let mut age_separator = None;
+
+        for (key, value) in params {
+            match key {
+                "from_timestamp" => initializers[0] = Some(Initializer::Timestamp(value.parse().map_err(invalid("seconds timestamp"))?)),
+                "from_timestamp_millis" => {
+                    initializers[1] = Some(Initializer::TimestampMillis(
+                        value.parse().map_err(invalid("milli-seconds timestamp"))?,
+                    ))
+                }
+                "from_timestamp_micros" => {
+                    initializers[2] = Some(Initializer::TimestampMicros(
+                        value.parse().map_err(invalid("micro-seconds timestamp"))?,
+                    ))
+                }
+                "from_timestamp_nanos" => {
+                    initializers[3] = Some(Initializer::TimestampNanos(
+                        value.parse().map_err(invalid("nano-seconds timestamp"))?,
+                    ))
+                }
+                "from_rfc2822" => {
+                    initializers[4] = Some(Initializer::Rfc2822(
+                        DateTime::parse_from_rfc2822(&value).map_err(invalid("RFC2822 datetime format"))?,
+                    ))
+                }
+                "from_rfc3339" => {
+                    initializers[5] = Some(Initializer::Rfc3339(
+                        DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?,
+                    ))
+                }
+                "from_str" => {
+                    initializers[6] = Some(Initializer::Str {
+                        input: value,
+                        format: String::new(),
+                    })
+                }
+                "input_format" => input_format = Some(value),
+                "from_rrule" => {
+                    initializers[7] = Some(Initializer::RRule {
+                        rule: Box::new(value.parse().map_err(invalid("recurrence rule"))?),
+                        dtstart: DateTime::UNIX_EPOCH.fixed_offset(),
+                        after: None,
+                    })
+                }
+                "from_cron" => {
+                    initializers[8] = Some(Initializer::Cron {
+                        cron: value.parse().map_err(invalid("cron expression"))?,
+                        occurrence: CronOccurrence::Next,
+                        after: None,
+                    })
+                }
+                "occurrence" => occurrence = Some(value.parse().map_err(invalid("occurrence parameter"))?),
+                "dtstart" => dtstart = Some(DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?),
+                "after" => after = Some(DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?),
+
+                "output_format" => output_format = Some(value),
+                "format_syntax" => format_syntax = Some(value.parse().map_err(invalid("format syntax parameter"))?),
+                "format" => preset = Some(value),
+                "date_style" => date_style = Some(value.parse().map_err(invalid("date style parameter"))?),
+                "time_style" => time_style = Some(value.parse().map_err(invalid("time style parameter"))?),
+                "locale" => spec.locale = Some(value),
+                "to_rfc2822" => flags[0] = Some(Finalizer::Rfc2822),
+                "to_timestamp" => flags[1] = Some(Finalizer::Timestamp),
+                "to_timestamp_millis" => flags[2] = Some(Finalizer::TimestampMillis),
+                "to_timestamp_micros" => flags[3] = Some(Finalizer::TimestampMicros),
+                "to_timestamp_nanos" => flags[4] = Some(Finalizer::TimestampNanos),
+                "years_since" => years_since = Some(DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?),
+                "business_days_until" => {
+                    business_days_until = Some(DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?)
+                }
+                "to_calendar" => to_calendar = true,
+                "calendar_base" => calendar_base = Some(DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?),
+                "calendar_same_day" => calendar_formats.same_day = Some(value),
+                "calendar_next_day" => calendar_formats.next_day = Some(value),
+                "calendar_last_day" => calendar_formats.last_day = Some(value),
+                "calendar_next_week" => calendar_formats.next_week = Some(value),
+                "calendar_last_week" => calendar_formats.last_week = Some(value),
+                "calendar_same_else" => calendar_formats.same_else = Some(value),
+                "age_since" => age_since = Some(DateTime::parse_from_rfc3339(&value).map_err(invalid("RFC3339 datetime format"))?),
+                "age_precision" => age_precision = Some(value.parse().map_err(invalid("age precision parameter"))?),
+                "age_output" => {
+                    age_json = match value.as_str() {
+                        "text" => false,
+                        "json" => true,
+                        _ => {
+                            return Err(format!(
+                                "Invalid age output parameter: {}. Supported values are text and json",
+                                value
+                            ))
+                        }
+                    }
+                }
+                "age_separator" => age_separator = Some(value),
+
+                key => spec
+                    .modifiers
+                    .set(key, &value)
+                    .ok_or_else(|| format!("Unknown hash parameter: {}", key))??,
+            }
+        }
+
+        spec.initializer = match initializers.into_iter().flatten().next() {
+            Some(Initializer::Str { input, .. }) => Initializer::Str {
+                input,
+                format: input_format.ok_or_else(|| "Missing `input_format` hash parameter".to_string())?,
+            },
+            Some(Initializer::RRule { rule, .. }) => Initializer::RRule {
+                rule,
+                dtstart: dtstart.ok_or_else(|| "Missing `dtstart` hash parameter".to_string())?,
+                after,
+            },
+            Some(Initializer::Cron { cron, .. }) => Initializer::Cron {
+                cron,
+                occurrence: occurrence.unwrap_or_default(),
+                after,
+            },
+            Some(initializer) => initializer,
+            None => Initializer::Now,
+        };
+
+        spec.finalizer = if let Some(format) = output_format {
+            Finalizer::OutputFormat {
+                format,
+                syntax: format_syntax,
+            }
+        } else if let Some(preset) = preset {
+            Finalizer::Preset(preset)
+        } else if date_style.is_some() || time_style.is_some() {
+            Finalizer::Style {
+                date: date_style,
+                time: time_style,
+            }
+        } else if let Some(flag) = flags.into_iter().flatten().next() {
+            flag
+        } else if let Some(base) = years_since {
+            Finalizer::YearsSince(base)
+        } else if let Some(until) = business_days_until {
+            Finalizer::BusinessDaysUntil(until)
+        } else if to_calendar {
+            Finalizer::Calendar {
+                base: calendar_base,
+                formats: calendar_formats,
+            }
+        } else if let Some(since) = age_since {
+            Finalizer::Age {
+                since,
+                precision: age_precision.unwrap_or_default(),
+                json: age_json,
+                separator: age_separator,
+            }
+        } else {
+            Finalizer::Rfc3339
+        };
+
+        Ok(spec)
+    }
+}
+
+/// Error produced by an invalid [`DateTimeSpec`], or when evaluating it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpecError(String);
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+impl Modifiers {
+    /// Sets a modifier by its hash parameter name, `None` if the name is unknown
+    fn set(&mut self, key: &str, value: &str) -> Option<Result<(), String>> {
+        let result = match key {
+            "weekend" => parse_weekdays(value)
+                .map(|weekend| self.weekend = Some(weekend))
+                .map_err(invalid("weekend parameter")),
+            "month_overflow" => value
+                .parse()
+                .map(|month_overflow| self.month_overflow = month_overflow)
+                .map_err(invalid("month overflow parameter")),
+            "with_timezone" => value.parse().map(|timezone| self.timezone = Some(timezone)),
+            "with_ordinal" => value
+                .parse()
+                .map(|day| self.ordinal = Some(day))
+                .map_err(invalid("ordinal parameter")),
+            "with_ordinal0" => value
+                .parse()
+                .map(|day| self.ordinal0 = Some(day))
+                .map_err(invalid("ordinal parameter")),
+            "with_year" => value.parse().map(|year| self.year = Some(year)).map_err(invalid("year parameter")),
+            "with_month" => value
+                .parse()
+                .map(|month| self.month = Some(month))
+                .map_err(invalid("month parameter")),
+            "with_month0" => value
+                .parse()
+                .map(|month| self.month0 = Some(month))
+                .map_err(invalid("month parameter")),
+            "with_day" => value.parse().map(|day| self.day = Some(day)).map_err(invalid("day parameter")),
+            "with_day0" => value.parse().map(|day| self.day0 = Some(day)).map_err(invalid("day parameter")),
+            "with_hour" => value.parse().map(|hour| self.hour = Some(hour)).map_err(invalid("hour parameter")),
+            "with_minute" => value
+                .parse()
+                .map(|min| self.minute = Some(min))
+                .map_err(invalid("minute parameter")),
+            "with_second" => value
+                .parse()
+                .map(|sec| self.second = Some(sec))
+                .map_err(invalid("second parameter")),
+            "with_nanosecond" => value
+                .parse()
+                .map(|nsec| self.nanosecond = Some(nsec))
+                .map_err(invalid("nano-second parameter")),
+            "next_weekday" => parse_weekday(value)
+                .map(|day| self.next_weekday = Some(day))
+                .map_err(invalid("next weekday parameter")),
+            "previous_weekday" => parse_weekday(value)
+                .map(|day| self.previous_weekday = Some(day))
+                .map_err(invalid("previous weekday parameter")),
+            "nth_weekday_of_month" => value
+                .parse()
+                .map(|nth| self.nth_weekday_of_month = Some(nth))
+                .map_err(invalid("nth weekday of month parameter")),
+            "add" => value
+                .parse()
+                .map(|duration| self.add_duration = Some(duration))
+                .map_err(invalid("duration parameter")),
+            "sub" => value
+                .parse()
+                .map(|duration| self.sub_duration = Some(duration))
+                .map_err(invalid("duration parameter")),
+            key => {
+                if let Some(unit) = key.strip_prefix("add_") {
+                    return self.add.parse(unit, value);
+                } else if let Some(unit) = key.strip_prefix("sub_") {
+                    return self.sub.parse(unit, value);
+                }
+
+                return None;
+            }
+        };
+
+        Some(result)
+    }
+}
+
+impl Shift {
+    /// Sets the given unit, `None` if the unit is unknown
+    fn parse(&mut self, unit: &str, value: &str) -> Option<Result<(), String>> {
+        let result = match unit {
+            "years" => value
+                .parse()
+                .map(|years| self.years = Some(years))
+                .map_err(invalid("years parameter")),
+            "quarters" => value
+                .parse()
+                .map(|quarters| self.quarters = Some(quarters))
+                .map_err(invalid("quarters parameter")),
+            "months" => value
+                .parse()
+                .map(|months| self.months = Some(months))
+                .map_err(invalid("months parameter")),
+            "weeks" => value.parse().map_err(invalid("weeks parameter")).and_then(|weeks| {
+                self.weeks = Some(TimeDelta::try_weeks(weeks).ok_or_else(|| "Weeks parameter out of range".to_string())?);
+                Ok(())
+            }),
+            "days" => value.parse().map(|days| self.days = Some(days)).map_err(invalid("days parameter")),
+            "business_days" => value
+                .parse()
+                .map(|days| self.business_days = Some(days))
+                .map_err(invalid("business days parameter")),
+            "hours" => parse_fractional_delta(value, NANOS_PER_HOUR)
+                .map(|hours| self.hours = Some(hours))
+                .map_err(invalid("hours parameter")),
+            "minutes" => parse_fractional_delta(value, NANOS_PER_MINUTE)
+                .map(|minutes| self.minutes = Some(minutes))
+                .map_err(invalid("minutes parameter")),
+            "seconds" => parse_fractional_delta(value, NANOS_PER_SECOND)
+                .map(|seconds| self.seconds = Some(seconds))
+                .map_err(invalid("seconds parameter")),
+            "milliseconds" => parse_fractional_delta(value, NANOS_PER_MILLISECOND)
+                .map(|msec| self.milliseconds = Some(msec))
+                .map_err(invalid("milli-seconds parameter")),
+            "microseconds" => parse_fractional_delta(value, NANOS_PER_MICROSECOND)
+                .map(|usec| self.microseconds = Some(usec))
+                .map_err(invalid("micro-seconds parameter")),
+            "nanoseconds" => value
+                .parse()
+                .map(|nsec| self.nanoseconds = Some(TimeDelta::nanoseconds(nsec)))
+                .map_err(invalid("nano-seconds parameter")),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+}
+
+/// Error message of an invalid parameter value
+fn invalid<E: Display>(what: &'static str) -> impl FnOnce(E) -> String {
+    move |e| format!("Invalid {}: {}", what, e)
+}
+
+/// Parses a signed, possibly fractional, amount of a time unit into an exact `TimeDelta`, as in `-1.5` hours
+///
+/// Anything below a nano-second is truncated.
+pub(crate) fn parse_fractional_delta(input: &str, unit_nanos: i128) -> Result<TimeDelta, String> {
+    let input = input.trim();
+    let (negative, digits) = match input.as_bytes().first() {
+        Some(b'-') => (true, &input[1..]),
+        Some(b'+') => (false, &input[1..]),
+        _ => (false, input),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+        || fraction.len() > 18
+    {
+        return Err(format!("expected a number, got `{}`", input));
+    }
+
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<i128>().map_err(|e| e.to_string())?
+    };
+    let scale = 10i128.pow(fraction.len() as u32);
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<i128>().map_err(|e| e.to_string())?
+    };
+
+    let nanos = whole
+        .checked_mul(unit_nanos)
+        .and_then(|nanos| nanos.checked_add(fraction.checked_mul(unit_nanos)? / scale))
+        .map(|nanos| if negative { -nanos } else { nanos })
+        .ok_or_else(|| "out of range".to_string())?;
+
+    let secs = i64::try_from(nanos.div_euclid(NANOS_PER_SECOND)).map_err(|_e| "out of range".to_string())?;
+
+    TimeDelta::new(secs, nanos.rem_euclid(NANOS_PER_SECOND) as u32).ok_or_else(|| "out of range".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(params: &[(&'static str, &str)]) -> Result<DateTimeSpec, String> {
+        DateTimeSpec::parse(params.iter().map(|(key, value)| (*key, value.to_string())))
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(parse(&[]), Ok(DateTimeSpec::default()));
+
+        let spec = parse(&[
+            ("from_rfc3339", "1989-08-09T09:30:11+02:00"),
+            ("from_timestamp", "618658211"),
+            ("add_days", "-2"),
+            ("sub_hours", "1.5"),
+            ("to_timestamp", "true"),
+            ("to_rfc2822", "true"),
+        ])
+        .expect("Parse error");
+
+        assert_eq!(spec.initializer, Initializer::Timestamp(618658211));
+        assert_eq!(spec.modifiers.add.days, Some(-2));
+        assert_eq!(spec.modifiers.sub.hours, Some(TimeDelta::minutes(90)));
+        assert_eq!(spec.finalizer, Finalizer::Rfc2822);
+
+        let spec = parse(&[("from_str", "1989"), ("input_format", "%Y"), ("time_style", "short")]).expect("Parse error");
+        assert_eq!(
+            spec.initializer,
+            Initializer::Str {
+                input: "1989".to_string(),
+                format: "%Y".to_string()
+            }
+        );
+        assert_eq!(
+            spec.finalizer,
+            Finalizer::Style {
+                date: None,
+                time: Some(Style::Short)
+            }
+        );
+
+        let spec = parse(&[
+            ("from_rrule", "FREQ=YEARLY"),
+            ("dtstart", "1989-08-09T09:30:11+02:00"),
+            ("after", "2024-01-01T00:00:00Z"),
+        ])
+        .expect("Parse error");
+        assert_eq!(
+            spec.evaluate().expect("Evaluate error"),
+            DateTime::parse_from_rfc3339("2024-08-09T07:30:11Z").expect("Parse error")
+        );
+
+        let spec = parse(&[
+            ("from_cron", "@monthly"),
+            ("occurrence", "previous"),
+            ("after", "2024-01-15T00:00:00Z"),
+        ])
+        .expect("Parse error");
+        assert_eq!(
+            spec.evaluate().expect("Evaluate error"),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").expect("Parse error")
+        );
+
+        let spec = parse(&[
+            ("from_timestamp", "618658211"),
+            ("calendar_last_day", "%H:%M yesterday"),
+            ("to_calendar", "true"),
+            ("calendar_base", "1989-08-10T09:30:11Z"),
+        ])
+        .expect("Parse error");
+        assert_eq!(
+            spec.finalizer,
+            Finalizer::Calendar {
+                base: DateTime::parse_from_rfc3339("1989-08-10T09:30:11Z").ok(),
+                formats: CalendarFormats {
+                    last_day: Some("%H:%M yesterday".to_string()),
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(spec.render().expect("Render error"), "09:30 yesterday");
+
+        assert_eq!(parse_fractional_delta("-0.5", NANOS_PER_SECOND), Ok(TimeDelta::milliseconds(-500)));
+
+        let spec = DateTimeSpec::new()
+            .with_initializer(Initializer::Rfc3339(
+                DateTime::parse_from_rfc3339("1989-08-09T09:30:11+02:00").expect("Parse error"),
+            ))
+            .with_modifier("with_timezone", "+02:00")
+            .expect("Invalid modifier")
+            .with_modifier("next_weekday", "Monday")
+            .expect("Invalid modifier")
+            .with_finalizer(Finalizer::Preset("sql_date".to_string()));
+
+        assert_eq!(
+            spec.evaluate().expect("Evaluate error"),
+            DateTime::parse_from_rfc3339("1989-08-14T09:30:11+02:00").expect("Parse error")
+        );
+        assert_eq!(spec.render().expect("Render error"), "1989-08-14");
+
+        let params = BTreeMap::from([
+            ("from_timestamp", JsonValue::from(618658211)),
+            ("to_timestamp_millis", JsonValue::from(true)),
+        ]);
+        let spec = DateTimeSpec::from_map(&params).expect("Parse error");
+        assert_eq!(spec.render().expect("Render error"), "618658211000");
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!(parse(&[("from_timestap", "618658211")]).is_err());
+        assert!(parse(&[("add_fortnights", "1")]).is_err());
+        assert!(parse(&[("from_str", "1989")]).is_err());
+        assert!(parse(&[("from_rrule", "FREQ=YEARLY")]).is_err());
+        assert!(parse(&[("age_since", "1951-01-07T00:00:00Z"), ("age_output", "xml")]).is_err());
+        assert!(parse(&[("add_weeks", "9223372036854775807")]).is_err());
+        assert!(parse_fractional_delta("1e3", NANOS_PER_SECOND).is_err());
+
+        assert!(DateTimeSpec::new().with_modifier("add_fortnights", "1").is_err());
+        assert!(DateTimeSpec::new().with_modifier("from_timestamp", "1").is_err());
+        assert!(DateTimeSpec::new().with_modifier("with_month", "13").is_ok());
+        assert!(DateTimeSpec::new()
+            .with_modifier("with_month", "13")
+            .and_then(|spec| spec.render())
+            .is_err());
+        assert!(DateTimeSpec::from_map(&BTreeMap::from([("from_str", JsonValue::from("1989"))])).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use std::str::FromStr;
+
+/// Date or time style, as in ICU and `Intl.DateTimeFormat`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Short,
+    Medium,
+    Long,
+    Full,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "short" => Ok(Self::Short),
+            "medium" => Ok(Self::Medium),
+            "long" => Ok(Self::Long),
+            "full" => Ok(Self::Full),
+            other => Err(format!("Expected `short`, `medium`, `long` or `full`, got: {}", other)),
+        }
+    }
+}
+
+/// Strftime patterns of a locale, derived from the CLDR date and time formats
+struct LocaleStyles {
+    /// short, medium, long and full date patterns
+    date: [&'static str; 4],
+    /// short, medium, long and full time patterns
+    time: [&'static str; 4],
+    /// placed between the date and the time when both are requested
+    separator: &'static str,
+}
+
+const TIME_24H: [&str; 4] = ["%H:%M", "%H:%M:%S", "%H:%M:%S %Z", "%H:%M:%S %Z"];
+const TIME_24H_UNPADDED: [&str; 4] = ["%-H:%M", "%-H:%M:%S", "%-H:%M:%S %Z", "%-H:%M:%S %Z"];
+
+/// Used for locales without bundled patterns, relying on the `d_fmt` and `t_fmt` of the locale
+const FALLBACK: LocaleStyles = LocaleStyles {
+    date: ["%x", "%x", "%-d %B %Y", "%A, %-d %B %Y"],
+    time: ["%H:%M", "%X", "%X %Z", "%X %Z"],
+    separator: " ",
+};
+
+/// Language of a locale, as in `de` for `de_DE` or `sr` for `sr_RS@latin`, English without a locale
+pub(crate) fn language(locale: Option<&str>) -> &str {
+    locale.and_then(|locale| locale.split(['_', '-', '@', '.']).next()).unwrap_or("en")
+}
+
+/// Looks up the patterns by full locale name first (ex. `en_GB`), then by language (ex. `en`)
+fn locale_styles(locale: &str) -> &'static LocaleStyles {
+    const EN_US: LocaleStyles = LocaleStyles {
+        date: ["%-m/%-d/%y", "%b %-d, %Y", "%B %-d, %Y", "%A, %B %-d, %Y"],
+        time: ["%-I:%M %p", "%-I:%M:%S %p", "%-I:%M:%S %p %Z", "%-I:%M:%S %p %Z"],
+        separator: ", ",
+    };
+    const EN_GB: LocaleStyles = LocaleStyles {
+        date: ["%d/%m/%Y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const DE: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%y", "%d.%m.%Y", "%-d. %B %Y", "%A, %-d. %B %Y"],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const FR: LocaleStyles = LocaleStyles {
+        date: ["%d/%m/%Y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const ES: LocaleStyles = LocaleStyles {
+        date: ["%-d/%-m/%y", "%-d %b %Y", "%-d de %B de %Y", "%A, %-d de %B de %Y"],
+        time: TIME_24H_UNPADDED,
+        separator: ", ",
+    };
+    const IT: LocaleStyles = LocaleStyles {
+        date: ["%d/%m/%y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const PT: LocaleStyles = LocaleStyles {
+        date: ["%d/%m/%Y", "%-d de %b de %Y", "%-d de %B de %Y", "%A, %-d de %B de %Y"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const NL: LocaleStyles = LocaleStyles {
+        date: ["%d-%m-%Y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const BG: LocaleStyles = LocaleStyles {
+        date: ["%-d.%m.%y г.", "%-d.%m.%Y г.", "%-d %B %Y г.", "%A, %-d %B %Y г."],
+        time: ["%-H:%M ч.", "%-H:%M:%S ч.", "%-H:%M:%S ч. %Z", "%-H:%M:%S ч. %Z"],
+        separator: ", ",
+    };
+    const RU: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%Y", "%-d %b %Y г.", "%-d %B %Y г.", "%A, %-d %B %Y г."],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const UK: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%y", "%-d %b %Y р.", "%-d %B %Y р.", "%A, %-d %B %Y р."],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const PL: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%Y", "%-d %b %Y", "%-d %B %Y", "%A, %-d %B %Y"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const CS: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%y", "%-d. %-m. %Y", "%-d. %B %Y", "%A %-d. %B %Y"],
+        time: TIME_24H_UNPADDED,
+        separator: " ",
+    };
+    const SV: LocaleStyles = LocaleStyles {
+        date: ["%Y-%m-%d", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const DA: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%Y", "%-d. %b %Y", "%-d. %B %Y", "%A den %-d. %B %Y"],
+        time: ["%H.%M", "%H.%M.%S", "%H.%M.%S %Z", "%H.%M.%S %Z"],
+        separator: " ",
+    };
+    const NB: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%Y", "%-d. %b %Y", "%-d. %B %Y", "%A %-d. %B %Y"],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const FI: LocaleStyles = LocaleStyles {
+        date: ["%-d.%-m.%Y", "%-d.%-m.%Y", "%-d. %B %Y", "%A %-d. %B %Y"],
+        time: ["%-H.%M", "%-H.%M.%S", "%-H.%M.%S %Z", "%-H.%M.%S %Z"],
+        separator: " ",
+    };
+    const EL: LocaleStyles = LocaleStyles {
+        date: ["%-d/%-m/%y", "%-d %b %Y", "%-d %B %Y", "%A %-d %B %Y"],
+        time: ["%-I:%M %p", "%-I:%M:%S %p", "%-I:%M:%S %p %Z", "%-I:%M:%S %p %Z"],
+        separator: " - ",
+    };
+    const RO: LocaleStyles = LocaleStyles {
+        date: ["%d.%m.%Y", "%-d %b %Y", "%-d %B %Y", "%A, %-d %B %Y"],
+        time: TIME_24H,
+        separator: ", ",
+    };
+    const HU: LocaleStyles = LocaleStyles {
+        date: ["%Y. %m. %d.", "%Y. %b %-d.", "%Y. %B %-d.", "%Y. %B %-d., %A"],
+        time: TIME_24H_UNPADDED,
+        separator: " ",
+    };
+    const TR: LocaleStyles = LocaleStyles {
+        date: ["%-d.%m.%Y", "%-d %b %Y", "%-d %B %Y", "%-d %B %Y %A"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const JA: LocaleStyles = LocaleStyles {
+        date: ["%Y/%m/%d", "%Y/%m/%d", "%Y年%-m月%-d日", "%Y年%-m月%-d日%A"],
+        time: TIME_24H_UNPADDED,
+        separator: " ",
+    };
+    const ZH: LocaleStyles = LocaleStyles {
+        date: ["%Y/%-m/%-d", "%Y年%-m月%-d日", "%Y年%-m月%-d日", "%Y年%-m月%-d日%A"],
+        time: TIME_24H,
+        separator: " ",
+    };
+    const KO: LocaleStyles = LocaleStyles {
+        date: ["%y. %-m. %-d.", "%Y. %-m. %-d.", "%Y년 %-m월 %-d일", "%Y년 %-m월 %-d일 %A"],
+        time: ["%p %-I:%M", "%p %-I:%M:%S", "%p %-I시 %M분 %S초 %Z", "%p %-I시 %M분 %S초 %Z"],
+        separator: " ",
+    };
+
+    match (locale, language(Some(locale))) {
+        ("en_GB" | "en_IE" | "en_AU" | "en_NZ" | "en_IN" | "en_ZA", _) => &EN_GB,
+        (_, "en") => &EN_US,
+        (_, "de") => &DE,
+        (_, "fr") => &FR,
+        (_, "es") => &ES,
+        (_, "it") => &IT,
+        (_, "pt") => &PT,
+        (_, "nl") => &NL,
+        (_, "bg") => &BG,
+        (_, "ru") => &RU,
+        (_, "uk") => &UK,
+        (_, "pl") => &PL,
+        (_, "cs") => &CS,
+        (_, "sv") => &SV,
+        (_, "da") => &DA,
+        (_, "nb" | "nn" | "no") => &NB,
+        (_, "fi") => &FI,
+        (_, "el") => &EL,
+        (_, "ro") => &RO,
+        (_, "hu") => &HU,
+        (_, "tr") => &TR,
+        (_, "ja") => &JA,
+        (_, "zh") => &ZH,
+        (_, "ko") => &KO,
+        _ => &FALLBACK,
+    }
+}
+
+/// Builds the strftime pattern for the requested date and/or time styles of a locale, as in `en_US`
+pub(crate) fn pattern(locale: &str, date_style: Option<Style>, time_style: Option<Style>) -> String {
+    let styles = locale_styles(locale);
+    let index = |style: Style| match style {
+        Style::Short => 0,
+        Style::Medium => 1,
+        Style::Long => 2,
+        Style::Full => 3,
+    };
+
+    match (date_style, time_style) {
+        (Some(date), Some(time)) => format!("{}{}{}", styles.date[index(date)], styles.separator, styles.time[index(time)]),
+        (Some(date), None) => styles.date[index(date)].to_string(),
+        (None, Some(time)) => styles.time[index(time)].to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        assert_eq!(pattern("en_US", Some(Style::Medium), None), "%b %-d, %Y");
+        assert_eq!(pattern("en_GB", Some(Style::Short), None), "%d/%m/%Y");
+        assert_eq!(pattern("de_DE", Some(Style::Long), Some(Style::Short)), "%-d. %B %Y, %H:%M");
+        assert_eq!(pattern("de_AT", None, Some(Style::Medium)), "%H:%M:%S");
+        assert_eq!(pattern("xx_XX", Some(Style::Short), None), "%x");
+        assert_eq!(pattern("en_US", None, None), "");
+
+        assert_eq!(language(Some("de_DE")), "de");
+        assert_eq!(language(Some("sr_RS@latin")), "sr");
+        assert_eq!(language(Some("pt-BR")), "pt");
+        assert_eq!(language(None), "en");
+
+        assert_eq!("FULL".parse::<Style>(), Ok(Style::Full));
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!("tiny".parse::<Style>().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use std::str::FromStr;
+
+/// Parses a comma separated list of weekdays, as in `sat,sun`
+pub(crate) fn parse_weekdays(input: &str) -> Result<Vec<Weekday>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|day| !day.is_empty())
+        .map(parse_weekday)
+        .collect()
+}
+
+/// Parses a weekday name, either full or abbreviated, as in `monday` or `mon`
+pub(crate) fn parse_weekday(input: &str) -> Result<Weekday, String> {
+    Weekday::from_str(input.trim()).map_err(|_e| format!("Invalid weekday: {}", input))
+}
+
+/// Occurrence of a weekday within a month, as in `2:tuesday` or `last:friday`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct NthWeekday {
+    /// 1 to 5, or `None` for the last occurrence
+    pub nth: Option<u8>,
+    pub weekday: Weekday,
+}
+
+impl FromStr for NthWeekday {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (nth, weekday) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected `<nth>:<weekday>` or `last:<weekday>`, got: {}", s))?;
+
+        let nth = match nth.trim().to_lowercase().as_str() {
+            "last" => None,
+            nth => match nth.parse::<u8>() {
+                Ok(nth @ 1..=5) => Some(nth),
+                _ => return Err(format!("Occurrence must be between 1 and 5 or `last`, got: {}", nth)),
+            },
+        };
+
+        Ok(Self {
+            nth,
+            weekday: parse_weekday(weekday)?,
+        })
+    }
+}
+
+impl NthWeekday {
+    /// Date of this occurrence in the given month, if the month has it
+    pub fn in_month(&self, year: i32, month: u32) -> Option<NaiveDate> {
+        match self.nth {
+            Some(nth) => NaiveDate::from_weekday_of_month_opt(year, month, self.weekday, nth),
+            None => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let last = first.checked_add_months(chrono::Months::new(1))?.pred_opt()?;
+
+                previous_or_same(last, self.weekday)
+            }
+        }
+    }
+}
+
+/// The first given weekday strictly after the date
+pub(crate) fn next(date: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let days = (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday() - 1) % 7 + 1;
+
+    date.checked_add_days(Days::new(days.into()))
+}
+
+/// The last given weekday strictly before the date
+pub(crate) fn previous(date: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let days = (7 + date.weekday().num_days_from_monday() - weekday.num_days_from_monday() - 1) % 7 + 1;
+
+    date.checked_sub_days(Days::new(days.into()))
+}
+
+/// The last given weekday on or before the date
+pub(crate) fn previous_or_same(date: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    if date.weekday() == weekday {
+        Some(date)
+    } else {
+        previous(date, weekday)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        // 2024-03-13 is a Wednesday
+        let wednesday = NaiveDate::from_ymd_opt(2024, 3, 13).unwrap();
+
+        assert_eq!(next(wednesday, Weekday::Thu), NaiveDate::from_ymd_opt(2024, 3, 14));
+        assert_eq!(next(wednesday, Weekday::Wed), NaiveDate::from_ymd_opt(2024, 3, 20));
+        assert_eq!(next(wednesday, Weekday::Mon), NaiveDate::from_ymd_opt(2024, 3, 18));
+        assert_eq!(previous(wednesday, Weekday::Tue), NaiveDate::from_ymd_opt(2024, 3, 12));
+        assert_eq!(previous(wednesday, Weekday::Wed), NaiveDate::from_ymd_opt(2024, 3, 6));
+        assert_eq!(previous(wednesday, Weekday::Fri), NaiveDate::from_ymd_opt(2024, 3, 8));
+
+        let second_tuesday = "2:tuesday".parse::<NthWeekday>().unwrap();
+        assert_eq!(second_tuesday.in_month(2024, 3), NaiveDate::from_ymd_opt(2024, 3, 12));
+
+        let last_friday = "last:fri".parse::<NthWeekday>().unwrap();
+        assert_eq!(last_friday.in_month(2024, 3), NaiveDate::from_ymd_opt(2024, 3, 29));
+        assert_eq!(last_friday.in_month(2024, 12), NaiveDate::from_ymd_opt(2024, 12, 27));
+        assert_eq!(last_friday.in_month(2024, 5), NaiveDate::from_ymd_opt(2024, 5, 31));
+
+        let fifth_monday = "5:monday".parse::<NthWeekday>().unwrap();
+        assert_eq!(fifth_monday.in_month(2024, 4), NaiveDate::from_ymd_opt(2024, 4, 29));
+        assert_eq!(fifth_monday.in_month(2024, 3), None);
+
+        assert_eq!(parse_weekdays("fri, Sat"), Ok(vec![Weekday::Fri, Weekday::Sat]));
+        assert_eq!(parse_weekdays(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn it_craps() {
+        assert!(parse_weekdays("sat,someday").is_err());
+        assert!("tuesday".parse::<NthWeekday>().is_err());
+        assert!("0:tuesday".parse::<NthWeekday>().is_err());
+        assert!("6:tuesday".parse::<NthWeekday>().is_err());
+        assert!("first:tuesday".parse::<NthWeekday>().is_err());
+        assert!("2:someday".parse::<NthWeekday>().is_err());
+    }
+}